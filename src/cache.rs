@@ -1,67 +1,393 @@
+use crate::glob;
 use crate::prelude::*;
 
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, LineWriter};
 use std::path::{Path, PathBuf};
 
-#[derive(Debug)]
-pub struct Cache {
-    prefix: Option<PathBuf>,
-    files: [String; MAX_CACHE_SIZE + 1],
+/// The section of `git status` that an entry was listed under.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Section {
+    Staged,
+    Unstaged,
+    Unmerged,
+    Untracked,
+    Ignored,
+}
+
+impl Section {
+    /// Infers the section from a two-letter status code.
+    pub fn from_xy(xy: &str) -> Self {
+        match xy {
+            "DD" | "AU" | "UD" | "UA" | "DU" | "AA" | "UU" => Self::Unmerged,
+            "??" => Self::Untracked,
+            "!!" => Self::Ignored,
+            _ if xy.starts_with('.') => Self::Unstaged,
+            _ => Self::Staged,
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Staged => "staged",
+            Self::Unstaged => "unstaged",
+            Self::Unmerged => "unmerged",
+            Self::Untracked => "untracked",
+            Self::Ignored => "ignored",
+        }
+    }
+}
+
+impl TryFrom<&str> for Section {
+    type Error = Error;
+    fn try_from(arg: &str) -> Result<Self> {
+        use Section::*;
+        let section = match arg {
+            "staged" => Staged,
+            "unstaged" => Unstaged,
+            "unmerged" => Unmerged,
+            "untracked" => Untracked,
+            "ignored" => Ignored,
+            _ => return Err(Error::InvalidCache),
+        };
+        Ok(section)
+    }
+}
+
+/// A group of cache entries that can be selected all at once.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Category {
+    Section(Section),
+    /// Entries with this letter on either side of their status code.
+    Code(u8),
+}
+
+impl Category {
+    pub fn from_arg(arg: &str) -> Option<Self> {
+        use Section::*;
+        let category = match arg {
+            "staged" => Self::Section(Staged),
+            "unstaged" => Self::Section(Unstaged),
+            "unmerged" | "U" => Self::Section(Unmerged),
+            "untracked" | "?" => Self::Section(Untracked),
+            "ignored" => Self::Section(Ignored),
+            "modified" | "M" => Self::Code(b'M'),
+            "added" | "A" => Self::Code(b'A'),
+            "deleted" | "D" => Self::Code(b'D'),
+            "renamed" | "R" => Self::Code(b'R'),
+            "copied" | "C" => Self::Code(b'C'),
+            "typechange" | "T" => Self::Code(b'T'),
+            _ => return None,
+        };
+        Some(category)
+    }
+}
+
+/// A single line of the cache file: a pathspec, and where it was
+/// found in `git status` if known.
+///
+/// Stored as `<section>\t<XY>\t<line>\t<pathspec>`, where unchanged
+/// sides of the XY code are written as `.` (like `git status
+/// --porcelain=v2`), and `line` is the line number of a `git grep`
+/// match. Renames and copies follow the pathspec with a NUL and the
+/// original path, also like `git status --porcelain=v2 -z`.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Entry {
+    pub section: Option<Section>,
+    pub xy: String,
+    pub line: Option<usize>,
+    pub path: String,
+    /// The path that a renamed or copied file came from.
+    pub orig: Option<String>,
+}
+
+impl Entry {
+    pub fn new(section: Section, xy: &str, path: &str) -> Self {
+        let (section, xy) = (Some(section), xy.replace(' ', "."));
+        Self { section, xy, path: path.to_string(), ..Default::default() }
+    }
+
+    fn is(&self, category: Category) -> bool {
+        let Some(section) = self.section else { return false };
+        let unmerged = section == Section::Unmerged;
+        let (x, y) = match self.xy.as_bytes() {
+            [x, y] => (*x, *y),
+            _ => return false,
+        };
+        match category {
+            Category::Section(Section::Staged) => {
+                !unmerged && x.is_ascii_alphabetic()
+            }
+            Category::Section(Section::Unstaged) => {
+                !unmerged && y.is_ascii_alphabetic()
+            }
+            Category::Section(v) => section == v,
+            Category::Code(c) => !unmerged && (x == c || y == c),
+        }
+    }
+}
+
+impl From<String> for Entry {
+    fn from(line: String) -> Self {
+        let parts: Vec<&str> = line.splitn(4, '\t').collect();
+        match parts[..] {
+            [section, xy, number, path] => {
+                let (path, orig) = match path.split_once('\0') {
+                    Some((path, orig)) => (path, Some(orig.to_string())),
+                    None => (path, None),
+                };
+                Self {
+                    section: Section::try_from(section).ok(),
+                    xy: xy.to_string(),
+                    line: number.parse().ok(),
+                    path: path.to_string(),
+                    orig,
+                }
+            }
+            [section, xy, path] => Self {
+                section: Section::try_from(section).ok(),
+                xy: xy.to_string(),
+                path: path.to_string(),
+                ..Default::default()
+            },
+            // cache files written by older versions only hold paths
+            _ => Self { path: line, ..Default::default() },
+        }
+    }
+}
+
+impl std::fmt::Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let section = self.section.map_or("", |v| v.as_str());
+        let line = self.line.map_or(String::new(), |v| v.to_string());
+        write!(f, "{section}\t{}\t{line}\t{}", self.xy, self.path)?;
+        match &self.orig {
+            Some(orig) => write!(f, "\0{orig}"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The kinds of things that `gitnu` numbers. Each kind is cached in its
+/// own file, so that listing one kind doesn't clobber the others.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Kind {
+    /// Pathspecs, listed by `git nu status`.
+    Files,
+    /// Commit hashes, listed by `git nu log`.
+    Commits,
+    /// Branch and tag names, listed by `git nu branch` and `git nu tag`.
+    Refs,
+    /// Stash commit hashes, listed by `git nu stash list`. These expand
+    /// to `stash@{N}`, since that is what `git stash` accepts.
+    Stashes,
 }
 
-impl Default for Cache {
-    fn default() -> Self {
-        Self { prefix: None, files: std::array::from_fn(|i| i.to_string()) }
+impl Kind {
+    fn file_name(&self) -> &'static str {
+        match self {
+            Self::Files => CACHE_FILE_NAME,
+            Self::Commits => "gitnu-commits.txt",
+            Self::Refs => "gitnu-refs.txt",
+            Self::Stashes => "gitnu-stashes.txt",
+        }
+    }
+
+    /// Path to the cache file of this kind.
+    pub fn path<P: AsRef<Path>>(&self, git_dir: &Path, cwd: P) -> PathBuf {
+        cwd.as_ref().join(git_dir).join(self.file_name())
+    }
+
+    /// Creates the cache file of this kind, to be written line by line.
+    pub fn writer<P: AsRef<Path>>(
+        &self,
+        git_dir: &Path,
+        cwd: P,
+    ) -> Result<LineWriter<File>> {
+        Ok(LineWriter::new(File::create(self.path(git_dir, cwd))?))
     }
 }
 
+#[derive(Debug, Default)]
+pub struct Cache {
+    cwd: PathBuf,
+    prefix: Option<PathBuf>,
+    files: Vec<Entry>,
+    commits: Vec<String>,
+    refs: Vec<String>,
+    stashes: Vec<String>,
+}
+
 impl Cache {
-    /// Initialize cache by reading the cache file in `git_dir`.
-    pub fn new<P>(git_dir: &PathBuf, cwd: P) -> Self
+    /// Initialize cache by reading the cache files in `git_dir`.
+    pub fn new<P>(git_dir: &Path, cwd: P) -> Self
     where
         P: AsRef<Path>,
     {
-        Self::try_read(git_dir, cwd).unwrap_or_default()
+        let mut cache =
+            Self::try_read(git_dir, &cwd).unwrap_or_else(|_| Self::empty(&cwd));
+        if let Ok(lines) = Self::lines(git_dir, &cwd, Kind::Commits) {
+            cache.commits = lines.collect();
+        }
+        if let Ok(lines) = Self::lines(git_dir, &cwd, Kind::Refs) {
+            cache.refs = lines.collect();
+        }
+        if let Ok(lines) = Self::lines(git_dir, &cwd, Kind::Stashes) {
+            cache.stashes = lines.collect();
+        }
+        cache
+    }
+
+    /// A cache without anything in it, for use outside of a repository.
+    pub fn empty<P: AsRef<Path>>(cwd: P) -> Self {
+        Self { cwd: cwd.as_ref().to_path_buf(), ..Default::default() }
     }
 
-    /// Try to read the cache file from `git_dir`.
-    fn try_read<P>(git_dir: &PathBuf, cwd: P) -> Result<Self>
+    /// Read the cache file of `kind` from `git_dir` line by line. Lines
+    /// that are not valid UTF-8 are read lossily so that the lines after
+    /// them keep their numbers.
+    fn lines<P>(
+        git_dir: &Path,
+        cwd: P,
+        kind: Kind,
+    ) -> Result<impl Iterator<Item = String>>
     where
         P: AsRef<Path>,
     {
-        let mut cache_path = cwd.as_ref().to_path_buf();
-        cache_path.push(git_dir);
-        cache_path.push(CACHE_FILE_NAME);
+        let f = File::open(kind.path(git_dir, cwd))?;
+        let lines = BufReader::new(f).split(b'\n').map_while(|v| v.ok());
+        Ok(lines.map(|v| String::from_utf8_lossy(&v).into_owned()))
+    }
 
-        let f = File::open(cache_path)?;
-        let mut lines = BufReader::new(f).lines().filter_map(|v| v.ok());
+    /// Try to read the file cache from `git_dir`.
+    fn try_read<P>(git_dir: &Path, cwd: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let mut lines = Self::lines(git_dir, &cwd, Kind::Files)?;
 
         let prefix = {
             let first_line = lines.next().ok_or(Error::InvalidCache)?;
             let prefix = PathBuf::from(first_line);
-            match pathdiff::diff_paths(prefix, cwd) {
+            match pathdiff::diff_paths(prefix, &cwd) {
                 Some(v) if v.as_os_str().is_empty() => None,
                 v => v,
             }
         };
 
-        let files = std::array::from_fn(|i| match i {
-            0 => "0".to_string(),
-            i => lines.next().unwrap_or_else(|| i.to_string()),
-        });
+        let files = lines.map(Entry::from).collect();
+
+        let cwd = cwd.as_ref().to_path_buf();
+        Ok(Self { cwd, prefix, files, ..Default::default() })
+    }
+
+    /// Cached values of a kind that is stored as plain lines.
+    fn values(&self, kind: Kind) -> &[String] {
+        match kind {
+            Kind::Files => &[],
+            Kind::Commits => &self.commits,
+            Kind::Refs => &self.refs,
+            Kind::Stashes => &self.stashes,
+        }
+    }
+
+    /// Number of values of `kind` held by the cache. Values are indexed
+    /// from 1 to `len(kind)` inclusive.
+    pub fn len(&self, kind: Kind) -> usize {
+        match kind {
+            Kind::Files => self.files.len(),
+            _ => self.values(kind).len(),
+        }
+    }
 
-        Ok(Self { prefix, files })
+    /// Checks if `path` exists relative to the current directory.
+    pub fn exists(&self, path: &str) -> bool {
+        self.cwd.join(path).symlink_metadata().is_ok()
     }
 
-    /// Append the `index`-th cached value into an ArgHolder.
-    pub fn load<A: ArgHolder>(&self, index: usize, argh: &mut A) {
-        match (&self.prefix, self.files.get(index)) {
-            (Some(prefix), Some(pathspec)) => {
-                argh.add_arg(prefix.join(pathspec))
+    /// Indices of all cached files that fall under `category`.
+    pub fn select(&self, category: Category) -> Vec<usize> {
+        let files = self.files.iter().enumerate();
+        files.filter(|(_, v)| v.is(category)).map(|(i, _)| i + 1).collect()
+    }
+
+    /// Indices of all cached values of `kind` that match `pattern`. Files
+    /// are matched by their path relative to the current directory.
+    pub fn glob(&self, kind: Kind, pattern: &str) -> Vec<usize> {
+        (1..=self.len(kind))
+            .filter(|i| match kind {
+                Kind::Files => {
+                    let path = self.shortest(&self.files[i - 1].path);
+                    glob::matches(pattern, &path)
+                }
+                _ => glob::matches(pattern, &self.values(kind)[i - 1]),
+            })
+            .collect()
+    }
+
+    /// Get the `index`-th cached value of `kind`.
+    pub fn get(&self, kind: Kind, index: usize) -> Option<String> {
+        let i = index.checked_sub(1)?;
+        match kind {
+            Kind::Files => Some(self.relative(&self.files.get(i)?.path)),
+            Kind::Stashes => {
+                (i < self.stashes.len()).then(|| format!("stash@{{{i}}}"))
             }
-            (None, Some(pathspec)) => argh.add_arg(pathspec),
-            _ => argh.add_arg(index.to_string()),
+            _ => self.values(kind).get(i).cloned(),
+        }
+    }
+
+    /// The path that the `index`-th cached file was renamed or copied
+    /// from, if it was.
+    pub fn orig(&self, index: usize) -> Option<String> {
+        let orig = self.entry(index)?.orig.as_ref()?;
+        Some(self.relative(orig))
+    }
+
+    /// A cached path, made relative to the current directory.
+    fn relative(&self, pathspec: &str) -> String {
+        match &self.prefix {
+            Some(prefix) => prefix.join(pathspec).to_string_lossy().to_string(),
+            None => pathspec.to_string(),
+        }
+    }
+
+    /// A cached path the shortest way from the current directory, such
+    /// as `b.rs` rather than `../src/b.rs` from `src`, for globs to
+    /// match. Directories keep their trailing `/`.
+    fn shortest(&self, pathspec: &str) -> String {
+        let Some(prefix) = &self.prefix else { return pathspec.to_string() };
+        let path = pathdiff::normalize(self.cwd.join(prefix).join(pathspec));
+        let diff = pathdiff::diff_paths(path, &self.cwd)
+            .unwrap_or_else(|| prefix.join(pathspec));
+        let diff = diff.to_string_lossy();
+        match (diff.as_ref(), pathspec.ends_with('/')) {
+            ("", _) => "./".to_string(),
+            (v, true) if !v.ends_with('/') => format!("{v}/"),
+            (v, _) => v.to_string(),
+        }
+    }
+
+    /// Index of the cached file at `path`, relative to the current
+    /// directory.
+    pub fn find(&self, path: &str) -> Option<usize> {
+        let path = Path::new(path).components();
+        (1..=self.files.len()).find(|i| match self.get(Kind::Files, *i) {
+            Some(v) => Path::new(&v).components().eq(path.clone()),
+            None => false,
+        })
+    }
+
+    /// The `index`-th cached file, as it was listed.
+    pub fn entry(&self, index: usize) -> Option<&Entry> {
+        self.files.get(index.checked_sub(1)?)
+    }
+
+    /// Append the `index`-th cached value of `kind` into an ArgHolder.
+    pub fn load<A: ArgHolder>(&self, kind: Kind, index: usize, argh: &mut A) {
+        match self.get(kind, index) {
+            Some(v) => argh.add_arg(v),
+            None => argh.add_arg(index.to_string()),
         };
     }
 }
@@ -0,0 +1,76 @@
+//! Shell completion scripts, printed by `git nu completions <shell>`.
+//!
+//! Each script completes the commands that `git nu` takes, and numbers
+//! with the files they refer to. Numbers are read from `git nu ls`, so
+//! they always match the cache.
+
+const BASH: &str = r#"# bash completion for `git nu`, for use with git's own completion.
+_git_nu() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" i
+    for ((i = 0; i < COMP_CWORD; i++)); do
+        case ${COMP_WORDS[i]} in nu | git-nu) break ;; esac
+    done
+    if ((COMP_CWORD == i + 1)); then
+        COMPREPLY=($(compgen -W "{COMMANDS}" -- "$cur"))
+        return
+    fi
+    COMPREPLY=()
+    local line
+    while IFS= read -r line; do
+        [[ $line == "$cur"* ]] && COMPREPLY+=("$line")
+    done < <(git nu ls 2>/dev/null)
+    # bash can't describe candidates, so the files are only shown while
+    # there is more than one number to pick from
+    if ((${#COMPREPLY[@]} == 1)); then
+        COMPREPLY=("${COMPREPLY[0]%% *}")
+    elif ((${#COMPREPLY[@]} == 0)); then
+        COMPREPLY=($(compgen -f -- "$cur"))
+    fi
+}
+complete -o bashdefault -o default -F _git_nu git-nu
+"#;
+
+const ZSH: &str = r#"#compdef git-nu
+# zsh completion for `git nu`, which git's own completion also uses.
+_git-nu() {
+    local -a commands files
+    if (( CURRENT == 2 )); then
+        commands=({COMMANDS})
+        _describe -t commands 'command' commands
+        return
+    fi
+    local line
+    for line in ${(f)"$(git nu ls 2>/dev/null)"}; do
+        files+=("${line%% *}:${${${line#* }##[[:space:]]#}//:/\\:}")
+    done
+    _describe -V -t numbers 'number' files
+    _files
+}
+compdef _git-nu git-nu
+"#;
+
+const FISH: &str = r#"# fish completion for `git nu`.
+function __gitnu_needs_command
+    set -l tokens (commandline -opc)
+    set -l i (contains -i -- nu $tokens)
+    test -n "$i"; and test (count $tokens) -eq $i
+end
+
+function __gitnu_numbers
+    git nu ls 2>/dev/null | string replace -r '^(\d+)\s+' '$1\t'
+end
+
+complete -c git -n '__fish_seen_subcommand_from nu; and __gitnu_needs_command' -f -a '{COMMANDS}'
+complete -c git -n '__fish_seen_subcommand_from nu; and not __gitnu_needs_command' -k -a '(__gitnu_numbers)'
+"#;
+
+/// The completion script for `shell`, if it is supported.
+pub fn script(shell: &str, commands: &[&str]) -> Option<String> {
+    let script = match shell {
+        "bash" => BASH,
+        "zsh" => ZSH,
+        "fish" => FISH,
+        _ => return None,
+    };
+    Some(script.replace("{COMMANDS}", &commands.join(" ")))
+}
@@ -0,0 +1,157 @@
+use crate::prelude::*;
+
+use std::collections::HashMap;
+use std::io::{stdout, IsTerminal};
+
+/// What to do with an argument that names an existing file, but can
+/// also be expanded from the cache. Set with `gitnu.collision`.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Collision {
+    /// Leave the argument as-is, so that it refers to the file.
+    #[default]
+    Path,
+    /// Expand the argument from the cache, and warn about it.
+    Warn,
+}
+
+impl TryFrom<&str> for Collision {
+    type Error = Error;
+    fn try_from(arg: &str) -> Result<Self> {
+        match arg {
+            "path" => Ok(Self::Path),
+            "warn" => Ok(Self::Warn),
+            _ => Err(Error::InvalidConfig),
+        }
+    }
+}
+
+/// How a command's output is paged, as set by `pager.<cmd>`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Paging {
+    /// Paged with the usual pager, or not at all.
+    Enabled(bool),
+    /// Paged with a pager of its own.
+    With(String),
+}
+
+impl From<&str> for Paging {
+    fn from(value: &str) -> Self {
+        match maybe_bool(value) {
+            Some(v) => Self::Enabled(v),
+            None => Self::With(value.to_string()),
+        }
+    }
+}
+
+/// Git configuration that `gitnu` cares about.
+#[derive(Debug, Default)]
+pub struct Config {
+    pub aliases: Aliases,
+    pub collision: Collision,
+    pub status: StatusConfig,
+    /// `grep.fullName`, which makes `git grep` show full file names.
+    pub grep_full_name: bool,
+    /// `grep.lineNumber`, which makes `git grep` show line numbers.
+    pub grep_line_number: bool,
+    /// `pager.<cmd>`, by the name of each command.
+    pub pagers: HashMap<String, Paging>,
+}
+
+impl Config {
+    /// Applies a single entry read from `git config`. Later entries
+    /// take precedence, just like in git.
+    pub fn set(&mut self, key: &str, value: &str) {
+        if let Some(alias) = key.strip_prefix("alias.") {
+            self.aliases.insert(alias.to_string(), value.to_string());
+        } else if key == "gitnu.collision" {
+            self.collision = value.try_into().unwrap_or_default();
+        } else if key == "grep.fullname" {
+            self.grep_full_name = bool(value);
+        } else if key == "grep.linenumber" {
+            self.grep_line_number = bool(value);
+        } else if let Some(cmd) = key.strip_prefix("pager.") {
+            self.pagers.insert(cmd.to_string(), value.into());
+        } else {
+            self.status.set(key, value);
+        }
+    }
+}
+
+/// Configuration of `git status` that changes how it lists files.
+#[derive(Debug, PartialEq, Clone)]
+pub struct StatusConfig {
+    /// `status.short`, which makes `git status` use the short format.
+    pub short: bool,
+    /// `status.relativePaths`, which shows paths relative to the
+    /// current directory rather than to the root of the workspace.
+    pub relative_paths: bool,
+    /// `core.quotePath`, which quotes paths with non-ASCII characters.
+    pub quote_path: bool,
+    /// `column.status`, and `column.ui` which it falls back to.
+    pub column: Option<String>,
+    pub column_ui: Option<String>,
+}
+
+impl Default for StatusConfig {
+    fn default() -> Self {
+        Self {
+            short: false,
+            relative_paths: true,
+            quote_path: true,
+            column: None,
+            column_ui: None,
+        }
+    }
+}
+
+impl StatusConfig {
+    pub fn set(&mut self, key: &str, value: &str) {
+        match key {
+            "status.short" => self.short = bool(value),
+            "status.relativepaths" => self.relative_paths = bool(value),
+            "core.quotepath" => self.quote_path = bool(value),
+            "column.status" => self.column = Some(value.to_string()),
+            "column.ui" => self.column_ui = Some(value.to_string()),
+            _ => {}
+        }
+    }
+
+    /// Checks if untracked files are listed in columns, which is off
+    /// unless configured.
+    pub fn column(&self) -> bool {
+        let value = self.column.as_ref().or(self.column_ui.as_ref());
+        value.and_then(|v| column(v)).unwrap_or(false)
+    }
+}
+
+/// Reads a list of column options, such as `always` or `auto,dense`, and
+/// checks if they turn columns on. Setting a layout without saying when
+/// turns them on, and `None` means the options say neither.
+pub fn column(value: &str) -> Option<bool> {
+    let mut enabled = None;
+    for option in value.split([' ', ',']).map(str::to_lowercase) {
+        match option.as_str() {
+            "always" => enabled = Some(true),
+            "never" => enabled = Some(false),
+            "auto" => enabled = Some(stdout().is_terminal()),
+            "column" | "row" | "plain" => enabled = enabled.or(Some(true)),
+            _ => {}
+        }
+    }
+    enabled
+}
+
+/// Reads a boolean config value the way git does. A key without a value
+/// counts as true.
+fn bool(value: &str) -> bool {
+    maybe_bool(value).unwrap_or(false)
+}
+
+/// Reads a config value that may be a boolean, or `None` if it isn't.
+fn maybe_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "" | "true" | "yes" | "on" => Some(true),
+        "false" | "no" | "off" => Some(false),
+        v => v.parse::<i64>().ok().map(|v| v != 0),
+    }
+}
@@ -1,14 +1,16 @@
-use std::any::Any;
 use std::io;
 
 #[derive(Debug)]
 pub enum Error {
+    InvalidArgument,
     InvalidCache,
+    InvalidConfig,
     NotGitCommand,
     NotGitRepository,
+    NotCached,
     NotImplemented,
+    StaleCache,
     Io(io::Error),
-    ThreadError(Box<dyn Any + Send + 'static>),
 }
 
 impl PartialEq for Error {
@@ -18,10 +20,13 @@ impl PartialEq for Error {
         match (lhs, rhs) {
             (NotGitRepository, NotGitRepository) => true,
             (NotImplemented, NotImplemented) => true,
+            (InvalidArgument, InvalidArgument) => true,
             (InvalidCache, InvalidCache) => true,
+            (InvalidConfig, InvalidConfig) => true,
             (NotGitCommand, NotGitCommand) => true,
+            (NotCached, NotCached) => true,
+            (StaleCache, StaleCache) => true,
             (Io(lhs), Io(rhs)) => lhs.kind() == rhs.kind(),
-            (ThreadError(_), ThreadError(_)) => true,
             _ => false,
         }
     }
@@ -42,4 +47,3 @@ macro_rules! error {
 }
 
 error!(Io, io::Error);
-error!(ThreadError, Box<dyn Any + Send + 'static>);
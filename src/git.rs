@@ -1,47 +1,146 @@
-use std::io::BufRead;
+use std::env;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Output};
 
 use crate::error;
-use crate::prelude::{Aliases, Error, Result};
+use crate::prelude::{Config, Error, Result};
 
 /// Run a git command in a particular directory. Defaults to process's cwd.
-fn sh<P: AsRef<Path>>(dir: Option<P>, args: &[&str]) -> Result<Output> {
+/// `globals` are git's own options, placed before the command.
+///
+/// Its output is meant to be read by `gitnu` rather than by the user,
+/// so it is run in the C locale, which leaves git's messages
+/// untranslated.
+fn sh<P: AsRef<Path>>(
+    dir: Option<P>,
+    globals: &[&str],
+    args: &[&str],
+) -> Result<Output> {
     let mut cmd = Command::new("git");
+    cmd.env("LC_ALL", "C");
     if let Some(dir) = dir {
         cmd.current_dir(dir);
     }
-    Ok(cmd.args(args).output()?)
+    Ok(cmd.args(globals).args(args).output()?)
 }
 
-/// Path to git's repository (not workspace)
-///   * .git/
-///   * .git/worktrees/<branch-name>/
-///
-/// current_dir is intentionally not supplied as it relies on the
-/// user's actual PWD or the value of git's `-C` flag, which is not
-/// visible to the `git-nu` binary.
+/// Directories that git works with, as seen from where `gitnu` runs.
+#[derive(Debug, Default)]
+pub(crate) struct Dirs {
+    /// Path to git's repository (not workspace)
+    ///   * .git/
+    ///   * .git/worktrees/<branch-name>/
+    pub git_dir: PathBuf,
+    /// The directory that git reports most paths relative to. This is
+    /// the current directory, unless it is outside of the workspace, in
+    /// which case it is the root of the workspace.
+    pub base: PathBuf,
+    /// Root of the workspace, which `git diff` and friends report paths
+    /// relative to.
+    pub toplevel: PathBuf,
+}
+
+/// Finds the directories that git works with.
 ///
-/// This can either be absolute or relative to cwd.
-pub(crate) fn dir<P: AsRef<Path>>(cwd: P) -> Result<PathBuf> {
-    let output = sh(Some(cwd), &["rev-parse", "--git-dir"])?;
+/// `cwd` should already account for any `-C` flags given to `git-nu`,
+/// and `globals` holds the rest of git's own options (`--git-dir`,
+/// `--work-tree`, ...), which are respected along with `$GIT_DIR` and
+/// `$GIT_WORK_TREE`.
+pub(crate) fn dirs<P: AsRef<Path>>(cwd: P, globals: &[&str]) -> Result<Dirs> {
+    let args = ["rev-parse", "--absolute-git-dir", "--show-cdup"];
+    let output = sh(Some(&cwd), globals, &args)?;
     if output.stderr.starts_with(b"fatal: not a git repository") {
         return error!(NotGitRepository);
     }
     let stdout = String::from_utf8_lossy(&output.stdout);
-    Ok(PathBuf::from(stdout.trim_end()))
-}
-
-pub(crate) fn aliases() -> Aliases {
-    let args = ["config", "--global", "--get-regexp", "^alias."];
-    match sh(None::<&str>, &args) {
-        Ok(v) => Aliases::from_iter(
-            v.stdout.lines().filter_map(|v| v.ok()).filter_map(|v| {
-                v.get(6..) // every lines starts with "alias."
-                    .and_then(|v| v.split_once(' '))
-                    .map(|(k, v)| (k.to_string(), v.to_string()))
-            }),
-        ),
-        Err(_) => Aliases::new(),
+    let mut lines = stdout.lines();
+    let git_dir = PathBuf::from(lines.next().unwrap_or_default());
+    let cdup = PathBuf::from(lines.next().unwrap_or_default());
+    let cwd = cwd.as_ref().to_path_buf();
+    // `--show-cdup` is only absolute when `cwd` is outside the workspace
+    if cdup.is_absolute() {
+        return Ok(Dirs { git_dir, base: cdup.clone(), toplevel: cdup });
+    }
+    let mut toplevel = cwd.clone();
+    cdup.components().for_each(|_| _ = toplevel.pop());
+    Ok(Dirs { git_dir, base: cwd, toplevel })
+}
+
+/// The editor that git would use, as set by `$GIT_EDITOR`,
+/// `core.editor`, `$VISUAL` or `$EDITOR`.
+pub(crate) fn editor<P: AsRef<Path>>(
+    cwd: P,
+    globals: &[&str],
+) -> Result<String> {
+    let output = sh(Some(cwd), globals, &["var", "GIT_EDITOR"])?;
+    if !output.status.success() {
+        eprint!("{}", String::from_utf8_lossy(&output.stderr));
+        return error!(InvalidConfig);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Hashes of all stashes in `git_dir`, newest first, as `git stash list`
+/// shows them.
+pub(crate) fn stashes(git_dir: &Path) -> Vec<String> {
+    let git_dir = git_dir.to_string_lossy();
+    let globals = ["--git-dir", &git_dir];
+    let args = ["stash", "list", "--format=%H"];
+    let Ok(output) = sh(None::<&str>, &globals, &args) else { return vec![] };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout.lines().map(|v| v.to_string()).collect()
+}
+
+/// Checks if `rev` names a tree in `git_dir`, such as a commit, which
+/// `git grep` searches when given one.
+pub(crate) fn is_tree<P: AsRef<Path>>(
+    cwd: P,
+    git_dir: &Path,
+    rev: &str,
+) -> bool {
+    let (git_dir, rev) = (git_dir.to_string_lossy(), format!("{rev}^{{tree}}"));
+    let globals = ["--git-dir", &git_dir];
+    let args = ["rev-parse", "--verify", "--quiet", &rev];
+    sh(Some(cwd), &globals, &args).is_ok_and(|v| v.status.success())
+}
+
+/// The pager that git would show output in, as set by `$GIT_PAGER`,
+/// `program` (from `pager.<cmd>`), `core.pager` or `$PAGER`. `None` if
+/// paging is turned off.
+pub(crate) fn pager<P: AsRef<Path>>(
+    cwd: P,
+    globals: &[&str],
+    program: Option<&str>,
+) -> Option<String> {
+    let pager = match program.filter(|_| env::var_os("GIT_PAGER").is_none()) {
+        Some(v) => v.to_string(),
+        None => {
+            let output = sh(Some(cwd), globals, &["var", "GIT_PAGER"]).ok()?;
+            String::from_utf8_lossy(&output.stdout).trim().to_string()
+        }
+    };
+    match pager.as_str() {
+        "" | "cat" => None,
+        _ => Some(pager),
+    }
+}
+
+/// Reads the configuration relevant to `gitnu`, as seen from `cwd`.
+/// This covers every scope: system, global, local, and anything set
+/// with `git -c`.
+pub(crate) fn config<P: AsRef<Path>>(cwd: P, globals: &[&str]) -> Config {
+    let mut config = Config::default();
+    let pattern = "^(alias\\.|gitnu\\.|status\\.(short|relativepaths)$|\
+        core\\.quotepath$|column\\.(ui|status)$|\
+        grep\\.(fullname|linenumber)$|pager\\.)";
+    let args = ["config", "-z", "--get-regexp", pattern];
+    let Ok(output) = sh(Some(cwd), globals, &args) else { return config };
+    // each entry is `<key>\n<value>\0`
+    for entry in output.stdout.split(|b| *b == 0) {
+        let entry = String::from_utf8_lossy(entry);
+        let (key, value) =
+            entry.split_once('\n').unwrap_or((entry.as_ref(), ""));
+        config.set(key, value);
     }
+    config
 }
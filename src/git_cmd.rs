@@ -1,66 +1,629 @@
+use crate::cache::Kind;
+use crate::config::{self, StatusConfig};
 use crate::{prelude::Aliases, Error, Result};
 
-#[derive(Debug, PartialEq, Clone)]
-pub(crate) enum GitStatus {
-    Short,
+/// The output format of `git status`.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub(crate) enum StatusFormat {
+    #[default]
     Normal,
+    Short,
+    /// `--porcelain`, which is meant for scripts.
+    Porcelain,
+}
+
+/// What `git status` shows, from its arguments and from git's
+/// configuration, which the arguments override.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub(crate) struct GitStatus {
+    pub format: StatusFormat,
+    /// Entries end with NUL instead of a newline, with `-z`.
+    null: bool,
+    /// Untracked files are listed in columns, with `--column`.
+    column: Option<bool>,
+    pub config: StatusConfig,
 }
 
 impl GitStatus {
     pub fn short(&mut self) {
-        *self = GitStatus::Short;
+        self.format = StatusFormat::Short;
+    }
+
+    pub fn normal(&mut self) {
+        self.format = StatusFormat::Normal;
+    }
+
+    /// Starts from git's configuration of `git status`.
+    pub fn configure(&mut self, config: &StatusConfig) {
+        if config.short {
+            self.short();
+        }
+        self.config = config.clone();
+    }
+
+    /// Updates the format from one of the command's arguments.
+    pub fn read(&mut self, arg: &str) {
+        match arg {
+            "--short" => self.short(),
+            "--long" | "--no-short" => self.normal(),
+            "--porcelain" => self.format = StatusFormat::Porcelain,
+            _ if arg.starts_with("--porcelain=") => {
+                self.format = StatusFormat::Porcelain
+            }
+            "--null" => self.null = true,
+            "--column" => self.column = Some(true),
+            "--no-column" => self.column = Some(false),
+            _ if arg.starts_with("--column=") => {
+                let value = &arg["--column=".len()..];
+                self.column = Some(config::column(value).unwrap_or(true))
+            }
+            _ if arg.starts_with("--") => {}
+            // short options can be grouped, as in `-sb`, up until `-u`
+            // takes the rest as its value
+            _ if arg.starts_with('-') => {
+                for c in arg.chars().skip(1) {
+                    match c {
+                        's' => self.short(),
+                        'z' => self.null = true,
+                        'u' => break,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Checks if the files are listed one per line, in a format meant
+    /// for people, which `gitnu` can number.
+    pub fn is_numbered(&self) -> bool {
+        match self.format {
+            StatusFormat::Normal => {
+                let column = self.column.unwrap_or(self.config.column());
+                !self.null && !column
+            }
+            StatusFormat::Short => !self.null,
+            StatusFormat::Porcelain => false,
+        }
     }
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum GitLog {
+    Oneline,
+    Normal,
+    /// A format that `gitnu` doesn't know the lines of, such as
+    /// `--format=%s`, which are left unnumbered.
+    Custom,
+}
+
+impl GitLog {
+    pub fn oneline(&mut self) {
+        *self = GitLog::Oneline;
+    }
+
+    pub fn normal(&mut self) {
+        *self = GitLog::Normal;
+    }
+
+    /// Updates the format from one of the command's arguments.
+    pub fn read(&mut self, arg: &str) {
+        let format = match arg {
+            "--oneline" => "oneline",
+            "--pretty" => "medium",
+            _ => match arg.split_once('=') {
+                Some(("--pretty" | "--format", format)) => format,
+                _ => return,
+            },
+        };
+        match format {
+            "oneline" => self.oneline(),
+            // formats that start each commit with `commit <hash>`
+            "short" | "medium" | "full" | "fuller" | "raw" => self.normal(),
+            _ => *self = GitLog::Custom,
+        }
+    }
+}
+
+/// How file names are listed by `git diff`, `git log`, `git show` and
+/// `git ls-files`, if at all.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub(crate) enum Names {
+    #[default]
+    None,
+    /// One name per line, as with `--name-only`.
+    Plain,
+    /// Tab-separated fields ending with a name, as with `--name-status`.
+    Tabbed,
+    /// `<name> | <changes>`, as with `--stat`.
+    Stat,
+}
+
+/// The file names listed by a command, and whether they are relative to
+/// the current directory or to the root of the workspace.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub(crate) struct GitNames {
+    pub names: Names,
+    pub relative: bool,
+}
+
+impl GitNames {
+    /// `git ls-files` always lists names relative to the current
+    /// directory.
+    fn ls_files() -> Self {
+        Self { names: Names::Plain, relative: true }
+    }
+
+    /// Updates the listing from one of the command's arguments.
+    pub fn read(&mut self, arg: &str) {
+        match arg {
+            "--name-only" => self.names = Names::Plain,
+            "--name-status" | "--numstat" => self.names = Names::Tabbed,
+            "--stat" => self.names = Names::Stat,
+            _ if arg.starts_with("--stat=") => self.names = Names::Stat,
+            // NUL-separated output is meant for scripts, not people
+            "-z" => self.names = Names::None,
+            "--relative" => self.relative = true,
+            _ if arg.starts_with("--relative=") => self.relative = true,
+            "--no-relative" | "--full-name" => self.relative = false,
+            _ => {}
+        }
+    }
+}
+
+/// The output format of `git grep`.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub(crate) struct GitGrep {
+    /// Matches are shown with their line numbers.
+    pub line_number: bool,
+    /// File names are shown above their matches, instead of on each one.
+    pub heading: bool,
+    /// Only file names are shown, without their matches.
+    pub names_only: bool,
+    /// File names are followed by their number of matches, with `-c`.
+    pub count: bool,
+    /// File names are not shown at all.
+    pub no_names: bool,
+    /// File names are relative to the root of the workspace.
+    pub full_name: bool,
+    /// File names are followed by NUL, which is meant for scripts.
+    pub null: bool,
+    /// Lines around the matches are shown, such as with `-C`.
+    pub context: bool,
+    /// The pattern was given, with `-e` or `-f`, or as an argument.
+    pattern: bool,
+    /// Arguments after the pattern and before `--`, which are trees to
+    /// search in if they name one, and pathspecs otherwise.
+    pub revs: Vec<String>,
+}
+
+impl GitGrep {
+    /// Updates the format from one of the command's arguments.
+    pub fn read(&mut self, arg: &str) {
+        match arg {
+            "--line-number" => self.line_number = true,
+            "--no-line-number" => self.line_number = false,
+            "--heading" => self.heading = true,
+            "--no-heading" => self.heading = false,
+            "--files-with-matches"
+            | "--name-only"
+            | "--files-without-match" => self.names_only = true,
+            "--count" => (self.names_only, self.count) = (true, true),
+            "--no-filename" => self.no_names = true,
+            "--full-name" => self.full_name = true,
+            "--null" => self.null = true,
+            "--function-context" | "--show-function" => self.context = true,
+            _ if ["--after-context", "--before-context", "--context"]
+                .iter()
+                .any(|v| arg.starts_with(v)) =>
+            {
+                self.context = true
+            }
+            _ if arg.starts_with("--") => {}
+            // short options can be grouped, up until one takes a value
+            _ if arg.starts_with('-') => {
+                for c in arg.chars().skip(1) {
+                    match c {
+                        'n' => self.line_number = true,
+                        'l' | 'L' => self.names_only = true,
+                        'c' => (self.names_only, self.count) = (true, true),
+                        'h' => self.no_names = true,
+                        'H' => self.no_names = false,
+                        'z' => self.null = true,
+                        'e' | 'f' => {
+                            self.pattern = true;
+                            break;
+                        }
+                        // `-3` is short for `-C 3`
+                        'W' | 'p' | '0'..='9' => self.context = true,
+                        'A' | 'B' | 'C' => {
+                            self.context = true;
+                            break;
+                        }
+                        'm' => break,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Reads an argument that isn't an option, which is the pattern
+    /// unless one was given with `-e` or `-f`.
+    pub fn positional(&mut self, arg: &str) {
+        match self.pattern {
+            true => self.revs.push(arg.to_string()),
+            false => self.pattern = true,
+        }
+    }
+}
+
+/// Whether `git branch` or `git tag` lists refs or acts on them.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub(crate) struct GitRefs {
+    list: bool,
+    edit: bool,
+}
+
+impl GitRefs {
+    /// Listing was asked for explicitly, with `--list`.
+    pub fn list(&mut self) {
+        self.list = true;
+    }
+
+    /// Refs are being created, deleted, renamed, or otherwise changed.
+    pub fn edit(&mut self) {
+        self.edit = true;
+    }
+
+    pub fn is_list(&self) -> bool {
+        self.list || !self.edit
+    }
+}
+
+/// The subcommand given to `git stash`, and whether any of its
+/// arguments were expanded from numbered stashes.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub(crate) struct GitStash {
+    subcommand: Option<String>,
+    numbered: bool,
+}
+
+impl GitStash {
+    /// Only the first argument that isn't an option is a subcommand.
+    pub fn subcommand(&mut self, arg: &str) {
+        self.subcommand.get_or_insert_with(|| arg.to_string());
+    }
+
+    pub fn numbered(&mut self) {
+        self.numbered = true;
+    }
+
+    pub fn is(&self, subcommand: &str) -> bool {
+        self.subcommand.as_deref() == Some(subcommand)
+    }
+
+    pub fn is_numbered(&self) -> bool {
+        self.numbered
+    }
+
+    /// Checks if the subcommand takes stashes, rather than pathspecs.
+    pub fn takes_stashes(&self) -> bool {
+        matches!(
+            self.subcommand.as_deref(),
+            Some("show" | "pop" | "apply" | "drop" | "branch")
+        )
+    }
+}
+
+/// Whether `git restore` restores the index, with `--staged`.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub(crate) struct GitRestore {
+    staged: bool,
+}
+
+impl GitRestore {
+    /// Updates the target from one of the command's arguments.
+    pub fn read(&mut self, arg: &str) {
+        match arg {
+            "--staged" => self.staged = true,
+            _ if arg.starts_with("--") => {}
+            // short options can be grouped, as in `-SW`, up until `-s`
+            // takes the rest as its value
+            _ if arg.starts_with('-') => {
+                for c in arg.chars().skip(1) {
+                    match c {
+                        'S' => self.staged = true,
+                        's' => break,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Options that take their value from the next argument: short options
+/// as a string of letters, and long options without their leading `--`.
+type ValueOptions = (&'static str, &'static [&'static str]);
+
+/// Shared by every command that shows diffs.
+#[rustfmt::skip]
+const DIFF: ValueOptions = ("UGSOI", &[
+    "unified", "inter-hunk-context", "output", "output-indicator-new",
+    "output-indicator-old", "output-indicator-context", "stat-width",
+    "stat-name-width", "stat-graph-width", "stat-count", "diff-algorithm",
+    "anchored", "word-diff-regex", "color-moved-ws", "diff-filter",
+    "find-object", "skip-to", "rotate-to", "ignore-matching-lines",
+    "src-prefix", "dst-prefix", "line-prefix", "ws-error-highlight",
+]);
+
+/// Shared by every command that walks through revisions.
+#[rustfmt::skip]
+const REVS: ValueOptions = ("nL", &[
+    "max-count", "skip", "since", "after", "until", "before", "author",
+    "committer", "grep", "grep-reflog", "min-age", "max-age", "exclude",
+]);
+
+/// Shared by every command that reads pathspecs from a file.
+const PATHSPEC: ValueOptions = ("", &["pathspec-from-file"]);
+
+/// Shared by every command that lists refs.
+#[rustfmt::skip]
+const REFS: ValueOptions = ("", &[
+    "contains", "no-contains", "merged", "no-merged", "points-at", "sort",
+    "format",
+]);
+
+/// Shared by every command that makes a merge.
+const MERGE: ValueOptions = ("sX", &["strategy", "strategy-option"]);
+
+/// Shared by every command that talks to a remote.
+#[rustfmt::skip]
+const REMOTE: ValueOptions = ("oj", &[
+    "depth", "deepen", "shallow-since", "shallow-exclude", "push-option",
+    "upload-pack", "receive-pack", "jobs", "negotiation-tip",
+    "server-option", "refmap", "force-with-lease",
+]);
+
 #[rustfmt::skip]
 #[derive(Debug, PartialEq, Clone)]
 pub (crate)enum GitCommand {
-    // full list found from running `git help --all`
+    // aliases that start with `!`, which git runs as shell commands, and
+    // the programs that `git nu exec` runs
+    Shell,
+    // `gitnu`'s own subcommands that only take files, such as `git nu ls`
+    Native,
+    // commands whose output `gitnu` numbers
     Status(GitStatus),
+    Log(GitLog, GitNames),
+    Show(GitLog, GitNames),
+    Diff(GitNames),
+    LsFiles(GitNames),
+    Grep(GitGrep),
+    Branch(GitRefs),
+    Tag(GitRefs),
+    Stash(GitStash),
+    // commands that unstage both paths of a rename with `--staged`
+    Restore(GitRestore),
+    // the rest of the list found from running `git help --all`
     Add, Am, Annotate, Apply, Archimport, Archive, Attributes, Bisect, Blame,
-    Branch, Bugreport, Bundle, CatFile, CheckAttr, CheckIgnore, CheckMailmap,
+    Bugreport, Bundle, CatFile, CheckAttr, CheckIgnore, CheckMailmap,
     CheckRefFormat, Checkout, CheckoutIndex, Cherry, CherryPick, Citool, Clean,
     Cli, Clone, Column, Commit, CommitGraph, CommitTree, Config, CountObjects,
     Credential, CredentialCache, CredentialStore, Cvsexportcommit, Cvsimport,
-    Cvsserver, Daemon, Describe, Diagnose, Diff, DiffFiles, DiffIndex, DiffTree,
+    Cvsserver, Daemon, Describe, Diagnose, DiffFiles, DiffIndex, DiffTree,
     Difftool, FastExport, FastImport, Fetch, FetchPack, FilterBranch,
     FmtMergeMsg, ForEachRef, ForEachRepo, FormatBundle, FormatChunk,
     FormatCommitGraph, FormatIndex, FormatPack, FormatPatch, FormatSignature,
-    Fsck, Gc, GetTarCommitId, Gitk, Gitweb, Grep, Gui, HashObject, Help, Hook,
-    Hooks, HttpBackend, Ignore, ImapSend, IndexPack, Init, Instaweb,
-    InterpretTrailers, Log, LsFiles, LsRemote, LsTree, Mailinfo, Mailmap,
-    Mailsplit, Maintenance, Merge, MergeBase, MergeFile, MergeIndex,
-    MergeOneFile, MergeTree, Mergetool, Mktag, Mktree, Modules, MultiPackIndex,
-    Mv, NameRev, Notes, P4, PackObjects, PackRedundant, PackRefs, PatchId,
-    ProtocolCapabilities, ProtocolCommon, ProtocolHttp, ProtocolPack,
-    ProtocolV2, Prune, PrunePacked, Pull, Push, Quiltimport, RangeDiff,
-    ReadTree, Rebase, Reflog, Remote, Repack, Replace, RepositoryLayout,
-    RequestPull, Rerere, Reset, Restore, RevList, RevParse, Revert, Revisions,
-    Rm, Scalar, SendEmail, SendPack, ShI18n, ShSetup, Shortlog, Show,
-    ShowBranch, ShowIndex, ShowRef, SparseCheckout, Stash, Stripspace,
-    Submodule, Svn, Switch, SymbolicRef, Tag, UnpackFile, UnpackObjects,
-    UpdateIndex, UpdateRef, UpdateServerInfo, Var, VerifyCommit, VerifyPack,
-    VerifyTag, Version, WhatChanged, Worktree, WriteTree
+    Fsck, Gc, GetTarCommitId, Gitk, Gitweb, Gui, HashObject, Help, Hook, Hooks,
+    HttpBackend, Ignore, ImapSend, IndexPack, Init, Instaweb, InterpretTrailers,
+    LsRemote, LsTree, Mailinfo, Mailmap, Mailsplit, Maintenance, Merge,
+    MergeBase, MergeFile, MergeIndex, MergeOneFile, MergeTree, Mergetool, Mktag,
+    Mktree, Modules, MultiPackIndex, Mv, NameRev, Notes, P4, PackObjects,
+    PackRedundant, PackRefs, PatchId, ProtocolCapabilities, ProtocolCommon,
+    ProtocolHttp, ProtocolPack, ProtocolV2, Prune, PrunePacked, Pull, Push,
+    Quiltimport, RangeDiff, ReadTree, Rebase, Reflog, Remote, Repack, Replace,
+    RepositoryLayout, RequestPull, Rerere, Reset, RevList, RevParse, Revert,
+    Revisions, Rm, Scalar, SendEmail, SendPack, ShI18n, ShSetup, Shortlog,
+    ShowBranch, ShowIndex, ShowRef, SparseCheckout, Stripspace, Submodule, Svn,
+    Switch, SymbolicRef, UnpackFile, UnpackObjects, UpdateIndex, UpdateRef,
+    UpdateServerInfo, Var, VerifyCommit, VerifyPack, VerifyTag, Version,
+    WhatChanged, Worktree, WriteTree
 }
 
 impl GitCommand {
-    pub fn skip_next_arg(&self, arg: &str) -> bool {
-        use GitCommand as G;
-        match (self, arg) {
-            (G::Log, "-n") => true,
+    /// All options of this command that take a value.
+    #[rustfmt::skip]
+    fn value_options(&self) -> &'static [ValueOptions] {
+        use GitCommand::*;
+        match self {
+            Add => &[PATHSPEC, ("", &["chmod"])],
+            Am => &[("", &["directory", "exclude", "include", "whitespace"])],
+            Apply => &[("", &[
+                "directory", "exclude", "include", "whitespace",
+                "build-fake-ancestor",
+            ])],
+            Archive => &[("o", &[
+                "output", "format", "prefix", "remote", "exec", "add-file",
+            ])],
+            Blame | Annotate => &[("LS", &[
+                "contents", "ignore-rev", "ignore-revs-file", "since",
+            ])],
+            Branch(_) => &[REFS, ("u", &["set-upstream-to"])],
+            Checkout => &[PATHSPEC, ("bB", &["orphan", "conflict"])],
+            CherryPick | Revert => &[MERGE, ("m", &["mainline", "cleanup"])],
+            Clean => &[("e", &["exclude"])],
+            Commit => &[PATHSPEC, ("mFCct", &[
+                "message", "file", "reuse-message", "reedit-message",
+                "fixup", "squash", "author", "date", "template", "cleanup",
+                "trailer",
+            ])],
+            Describe => &[("", &["match", "exclude", "candidates"])],
+            Diff(_) | DiffFiles | DiffIndex | DiffTree => &[DIFF],
+            Difftool => &[DIFF, ("tx", &["tool", "extcmd"])],
+            Fetch | Push => &[REMOTE],
+            FormatPatch => &[DIFF, REVS, ("ov", &[
+                "output-directory", "subject-prefix", "to", "cc",
+                "in-reply-to", "base", "reroll-count", "signature",
+                "signature-file", "suffix", "cover-from-description",
+            ])],
+            Grep(_) => &[("efABCm", &[
+                "after-context", "before-context", "context", "max-count",
+                "max-depth", "threads",
+            ])],
+            Log(..) | Show(..) | WhatChanged | Reflog => &[DIFF, REVS],
+            LsFiles(_) => &[("xX", &[
+                "exclude", "exclude-from", "exclude-per-directory", "format",
+                "with-tree",
+            ])],
+            Merge => &[MERGE, ("mF", &[
+                "message", "file", "cleanup", "into-name",
+            ])],
+            Mergetool => &[("t", &["tool"])],
+            Notes => &[("mFCc", &[
+                "message", "file", "reuse-message", "reedit-message", "ref",
+            ])],
+            Pull => &[REMOTE, MERGE],
+            RangeDiff => &[DIFF, ("", &["creation-factor"])],
+            Rebase => &[MERGE, ("x", &["onto", "exec", "whitespace"])],
+            Reset | Rm => &[PATHSPEC],
+            Restore(_) => &[PATHSPEC, ("s", &["source", "conflict"])],
+            RevList | Shortlog => &[REVS, ("", &["group"])],
+            Stash(_) => &[PATHSPEC, ("m", &["message"])],
+            Switch => &[("cC", &[
+                "create", "force-create", "orphan", "conflict",
+            ])],
+            Tag(_) => &[REFS, ("mFu", &[
+                "message", "file", "local-user", "cleanup",
+            ])],
+            Worktree => &[("bB", &["reason"])],
+            _ => &[],
+        }
+    }
+
+    /// What numbers given to this command refer to. Anything after `--`
+    /// always refers to files.
+    pub fn kind(&self) -> Kind {
+        use GitCommand::*;
+        match self {
+            Show(..) | CherryPick | Revert | Rebase | FormatPatch
+            | RangeDiff | MergeBase | Cherry | Describe => Kind::Commits,
+            Branch(_) | Tag(_) | Switch | Merge => Kind::Refs,
+            Stash(v) if v.takes_stashes() => Kind::Stashes,
+            _ => Kind::Files,
+        }
+    }
+
+    /// Checks if every argument of the command that isn't an option is a
+    /// pathspec, and never a ref.
+    pub fn takes_only_paths(&self) -> bool {
+        use GitCommand::*;
+        matches!(
+            self,
+            Native | Add | Rm | Mv | Clean | Commit | Restore(_) | LsFiles(_)
+        )
+    }
+
+    /// For the commands whose output `gitnu` numbers, the name that
+    /// `pager.<name>` pages the command by, and whether git shows its
+    /// output in a pager by default when it goes to a terminal.
+    pub fn pager(&self) -> Option<(&'static str, bool)> {
+        use GitCommand::*;
+        let pager = match self {
+            Status(_) => ("status", false),
+            Log(..) => ("log", true),
+            Show(..) => ("show", true),
+            Diff(_) => ("diff", true),
+            LsFiles(_) => ("ls-files", false),
+            Grep(_) => ("grep", true),
+            Branch(_) => ("branch", true),
+            Tag(_) => ("tag", true),
+            Stash(_) => ("stash", true),
+            _ => return None,
+        };
+        Some(pager)
+    }
+
+    /// Checks if the command unstages files, which takes both paths of
+    /// a rename.
+    pub fn unstages(&self) -> bool {
+        match self {
+            GitCommand::Reset => true,
+            GitCommand::Restore(v) => v.staged,
             _ => false,
         }
     }
 
-    pub fn from_arg(aliases: &Aliases, arg: &str) -> Option<Self> {
-        match Self::try_from(arg) {
-            Ok(v) => Some(v),
-            _ => match aliases.get(arg).map(Self::try_from) {
-                Some(Ok(v)) => Some(v),
-                _ => None,
-            },
+    /// Checks if `arg` is an option that takes the next argument as its
+    /// value, such as `-m` in `git commit -m 3`.
+    pub fn takes_value(&self, arg: &str) -> bool {
+        let opts = self.value_options();
+        if let Some(long) = arg.strip_prefix("--") {
+            // `--option=value` holds its own value
+            return !long.contains('=')
+                && opts.iter().any(|v| v.1.contains(&long));
+        }
+        let Some(short) = arg.strip_prefix('-') else { return false };
+        // Short options can be grouped (`-am`) and hold their own value
+        // when it is attached (`-m3`).
+        for (i, c) in short.char_indices() {
+            if opts.iter().any(|v| v.0.contains(c)) {
+                return i + c.len_utf8() == short.len();
+            }
+        }
+        false
+    }
+
+    /// Finds the git command that `arg` runs, following any aliases.
+    /// Also returns the arguments that the aliases imply, such as `-s`
+    /// from `alias.st = status -s`.
+    pub fn from_arg(
+        aliases: &Aliases,
+        arg: &str,
+    ) -> Option<(Self, Vec<String>)> {
+        let (mut arg, mut implied) = (arg.to_string(), vec![]);
+        // aliases can refer to each other, so bound the number of hops
+        for _ in 0..=aliases.len() {
+            if let Ok(v) = Self::try_from(arg.as_str()) {
+                return Some((v, implied));
+            }
+            let alias = aliases.get(&arg)?;
+            if alias.starts_with('!') {
+                return Some((Self::Shell, vec![]));
+            }
+            let mut words = split_cmdline(alias)?.into_iter();
+            arg = words.next()?;
+            implied = words.chain(implied).collect();
+        }
+        None
+    }
+}
+
+/// Splits an alias into words the way git does: on whitespace, except
+/// within quotes. Returns `None` if a quote is left open.
+fn split_cmdline(cmdline: &str) -> Option<Vec<String>> {
+    let (mut words, mut word) = (vec![], None::<String>);
+    let (mut chars, mut quote) = (cmdline.chars(), None);
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (Some('\''), c) => word.get_or_insert_with(String::new).push(c),
+            (_, '\\') => {
+                word.get_or_insert_with(String::new).extend(chars.next())
+            }
+            (_, c) => word.get_or_insert_with(String::new).push(c),
+        }
+        if quote.is_some() {
+            word.get_or_insert_with(String::new);
         }
     }
+    words.extend(word);
+    quote.is_none().then_some(words)
 }
 
 impl TryFrom<&String> for GitCommand {
@@ -70,177 +633,191 @@ impl TryFrom<&String> for GitCommand {
     }
 }
 
-impl TryFrom<&str> for GitCommand {
-    type Error = Error;
-    fn try_from(arg: &str) -> Result<Self> {
-        use GitCommand::*;
-        let command = match arg {
-            "status" => Status(GitStatus::Normal),
-            "add" => Add,
-            "am" => Am,
-            "annotate" => Annotate,
-            "apply" => Apply,
-            "archimport" => Archimport,
-            "archive" => Archive,
-            "attributes" => Attributes,
-            "bisect" => Bisect,
-            "blame" => Blame,
-            "branch" => Branch,
-            "bugreport" => Bugreport,
-            "bundle" => Bundle,
-            "cat-file" => CatFile,
-            "check-attr" => CheckAttr,
-            "check-ignore" => CheckIgnore,
-            "check-mailmap" => CheckMailmap,
-            "check-ref-format" => CheckRefFormat,
-            "checkout" => Checkout,
-            "checkout-index" => CheckoutIndex,
-            "cherry" => Cherry,
-            "cherry-pick" => CherryPick,
-            "citool" => Citool,
-            "clean" => Clean,
-            "cli" => Cli,
-            "clone" => Clone,
-            "column" => Column,
-            "commit" => Commit,
-            "commit-graph" => CommitGraph,
-            "commit-tree" => CommitTree,
-            "config" => Config,
-            "count-objects" => CountObjects,
-            "credential" => Credential,
-            "credential-cache" => CredentialCache,
-            "credential-store" => CredentialStore,
-            "cvsexportcommit" => Cvsexportcommit,
-            "cvsimport" => Cvsimport,
-            "cvsserver" => Cvsserver,
-            "daemon" => Daemon,
-            "describe" => Describe,
-            "diagnose" => Diagnose,
-            "diff" => Diff,
-            "diff-files" => DiffFiles,
-            "diff-index" => DiffIndex,
-            "diff-tree" => DiffTree,
-            "difftool" => Difftool,
-            "fast-export" => FastExport,
-            "fast-import" => FastImport,
-            "fetch" => Fetch,
-            "fetch-pack" => FetchPack,
-            "filter-branch" => FilterBranch,
-            "fmt-merge-msg" => FmtMergeMsg,
-            "for-each-ref" => ForEachRef,
-            "for-each-repo" => ForEachRepo,
-            "format-bundle" => FormatBundle,
-            "format-chunk" => FormatChunk,
-            "format-commit-graph" => FormatCommitGraph,
-            "format-index" => FormatIndex,
-            "format-pack" => FormatPack,
-            "format-patch" => FormatPatch,
-            "format-signature" => FormatSignature,
-            "fsck" => Fsck,
-            "gc" => Gc,
-            "get-tar-commit-id" => GetTarCommitId,
-            "gitk" => Gitk,
-            "gitweb" => Gitweb,
-            "grep" => Grep,
-            "gui" => Gui,
-            "hash-object" => HashObject,
-            "help" => Help,
-            "hook" => Hook,
-            "hooks" => Hooks,
-            "http-backend" => HttpBackend,
-            "ignore" => Ignore,
-            "imap-send" => ImapSend,
-            "index-pack" => IndexPack,
-            "init" => Init,
-            "instaweb" => Instaweb,
-            "interpret-trailers" => InterpretTrailers,
-            "log" => Log,
-            "ls-files" => LsFiles,
-            "ls-remote" => LsRemote,
-            "ls-tree" => LsTree,
-            "mailinfo" => Mailinfo,
-            "mailmap" => Mailmap,
-            "mailsplit" => Mailsplit,
-            "maintenance" => Maintenance,
-            "merge" => Merge,
-            "merge-base" => MergeBase,
-            "merge-file" => MergeFile,
-            "merge-index" => MergeIndex,
-            "merge-one-file" => MergeOneFile,
-            "merge-tree" => MergeTree,
-            "mergetool" => Mergetool,
-            "mktag" => Mktag,
-            "mktree" => Mktree,
-            "modules" => Modules,
-            "multi-pack-index" => MultiPackIndex,
-            "mv" => Mv,
-            "name-rev" => NameRev,
-            "notes" => Notes,
-            "p4" => P4,
-            "pack-objects" => PackObjects,
-            "pack-redundant" => PackRedundant,
-            "pack-refs" => PackRefs,
-            "patch-id" => PatchId,
-            "protocol-capabilities" => ProtocolCapabilities,
-            "protocol-common" => ProtocolCommon,
-            "protocol-http" => ProtocolHttp,
-            "protocol-pack" => ProtocolPack,
-            "protocol-v2" => ProtocolV2,
-            "prune" => Prune,
-            "prune-packed" => PrunePacked,
-            "pull" => Pull,
-            "push" => Push,
-            "quiltimport" => Quiltimport,
-            "range-diff" => RangeDiff,
-            "read-tree" => ReadTree,
-            "rebase" => Rebase,
-            "reflog" => Reflog,
-            "remote" => Remote,
-            "repack" => Repack,
-            "replace" => Replace,
-            "repository-layout" => RepositoryLayout,
-            "request-pull" => RequestPull,
-            "rerere" => Rerere,
-            "reset" => Reset,
-            "restore" => Restore,
-            "rev-list" => RevList,
-            "rev-parse" => RevParse,
-            "revert" => Revert,
-            "revisions" => Revisions,
-            "rm" => Rm,
-            "scalar" => Scalar,
-            "send-email" => SendEmail,
-            "send-pack" => SendPack,
-            "sh-i18n" => ShI18n,
-            "sh-setup" => ShSetup,
-            "shortlog" => Shortlog,
-            "show" => Show,
-            "show-branch" => ShowBranch,
-            "show-index" => ShowIndex,
-            "show-ref" => ShowRef,
-            "sparse-checkout" => SparseCheckout,
-            "stash" => Stash,
-            "stripspace" => Stripspace,
-            "submodule" => Submodule,
-            "svn" => Svn,
-            "switch" => Switch,
-            "symbolic-ref" => SymbolicRef,
-            "tag" => Tag,
-            "unpack-file" => UnpackFile,
-            "unpack-objects" => UnpackObjects,
-            "update-index" => UpdateIndex,
-            "update-ref" => UpdateRef,
-            "update-server-info" => UpdateServerInfo,
-            "var" => Var,
-            "verify-commit" => VerifyCommit,
-            "verify-pack" => VerifyPack,
-            "verify-tag" => VerifyTag,
-            "version" | "--version" => Version,
-            "whatchanged" => WhatChanged,
-            "worktree" => Worktree,
-            "write-tree" => WriteTree,
-            _ => return Err(Error::NotGitCommand),
-        };
-        Ok(command)
-    }
+/// Generates the list of git's command names, and the conversion from a
+/// name to its command.
+macro_rules! commands {
+    ($($name:literal => $cmd:expr,)*) => {
+        /// Names of all git commands, from running `git help --all`.
+        pub const COMMANDS: &[&str] = &[$($name),*];
+
+        impl TryFrom<&str> for GitCommand {
+            type Error = Error;
+            fn try_from(arg: &str) -> Result<Self> {
+                use GitCommand::*;
+                let command = match arg {
+                    $($name => $cmd,)*
+                    "--version" => Version,
+                    _ => return Err(Error::NotGitCommand),
+                };
+                Ok(command)
+            }
+        }
+    };
+}
+
+commands! {
+    "status" => Status(GitStatus::default()),
+    "add" => Add,
+    "am" => Am,
+    "annotate" => Annotate,
+    "apply" => Apply,
+    "archimport" => Archimport,
+    "archive" => Archive,
+    "attributes" => Attributes,
+    "bisect" => Bisect,
+    "blame" => Blame,
+    "branch" => Branch(GitRefs::default()),
+    "bugreport" => Bugreport,
+    "bundle" => Bundle,
+    "cat-file" => CatFile,
+    "check-attr" => CheckAttr,
+    "check-ignore" => CheckIgnore,
+    "check-mailmap" => CheckMailmap,
+    "check-ref-format" => CheckRefFormat,
+    "checkout" => Checkout,
+    "checkout-index" => CheckoutIndex,
+    "cherry" => Cherry,
+    "cherry-pick" => CherryPick,
+    "citool" => Citool,
+    "clean" => Clean,
+    "cli" => Cli,
+    "clone" => Clone,
+    "column" => Column,
+    "commit" => Commit,
+    "commit-graph" => CommitGraph,
+    "commit-tree" => CommitTree,
+    "config" => Config,
+    "count-objects" => CountObjects,
+    "credential" => Credential,
+    "credential-cache" => CredentialCache,
+    "credential-store" => CredentialStore,
+    "cvsexportcommit" => Cvsexportcommit,
+    "cvsimport" => Cvsimport,
+    "cvsserver" => Cvsserver,
+    "daemon" => Daemon,
+    "describe" => Describe,
+    "diagnose" => Diagnose,
+    "diff" => Diff(GitNames::default()),
+    "diff-files" => DiffFiles,
+    "diff-index" => DiffIndex,
+    "diff-tree" => DiffTree,
+    "difftool" => Difftool,
+    "fast-export" => FastExport,
+    "fast-import" => FastImport,
+    "fetch" => Fetch,
+    "fetch-pack" => FetchPack,
+    "filter-branch" => FilterBranch,
+    "fmt-merge-msg" => FmtMergeMsg,
+    "for-each-ref" => ForEachRef,
+    "for-each-repo" => ForEachRepo,
+    "format-bundle" => FormatBundle,
+    "format-chunk" => FormatChunk,
+    "format-commit-graph" => FormatCommitGraph,
+    "format-index" => FormatIndex,
+    "format-pack" => FormatPack,
+    "format-patch" => FormatPatch,
+    "format-signature" => FormatSignature,
+    "fsck" => Fsck,
+    "gc" => Gc,
+    "get-tar-commit-id" => GetTarCommitId,
+    "gitk" => Gitk,
+    "gitweb" => Gitweb,
+    "grep" => Grep(GitGrep::default()),
+    "gui" => Gui,
+    "hash-object" => HashObject,
+    "help" => Help,
+    "hook" => Hook,
+    "hooks" => Hooks,
+    "http-backend" => HttpBackend,
+    "ignore" => Ignore,
+    "imap-send" => ImapSend,
+    "index-pack" => IndexPack,
+    "init" => Init,
+    "instaweb" => Instaweb,
+    "interpret-trailers" => InterpretTrailers,
+    "log" => Log(GitLog::Normal, GitNames::default()),
+    "ls-files" => LsFiles(GitNames::ls_files()),
+    "ls-remote" => LsRemote,
+    "ls-tree" => LsTree,
+    "mailinfo" => Mailinfo,
+    "mailmap" => Mailmap,
+    "mailsplit" => Mailsplit,
+    "maintenance" => Maintenance,
+    "merge" => Merge,
+    "merge-base" => MergeBase,
+    "merge-file" => MergeFile,
+    "merge-index" => MergeIndex,
+    "merge-one-file" => MergeOneFile,
+    "merge-tree" => MergeTree,
+    "mergetool" => Mergetool,
+    "mktag" => Mktag,
+    "mktree" => Mktree,
+    "modules" => Modules,
+    "multi-pack-index" => MultiPackIndex,
+    "mv" => Mv,
+    "name-rev" => NameRev,
+    "notes" => Notes,
+    "p4" => P4,
+    "pack-objects" => PackObjects,
+    "pack-redundant" => PackRedundant,
+    "pack-refs" => PackRefs,
+    "patch-id" => PatchId,
+    "protocol-capabilities" => ProtocolCapabilities,
+    "protocol-common" => ProtocolCommon,
+    "protocol-http" => ProtocolHttp,
+    "protocol-pack" => ProtocolPack,
+    "protocol-v2" => ProtocolV2,
+    "prune" => Prune,
+    "prune-packed" => PrunePacked,
+    "pull" => Pull,
+    "push" => Push,
+    "quiltimport" => Quiltimport,
+    "range-diff" => RangeDiff,
+    "read-tree" => ReadTree,
+    "rebase" => Rebase,
+    "reflog" => Reflog,
+    "remote" => Remote,
+    "repack" => Repack,
+    "replace" => Replace,
+    "repository-layout" => RepositoryLayout,
+    "request-pull" => RequestPull,
+    "rerere" => Rerere,
+    "reset" => Reset,
+    "restore" => Restore(GitRestore::default()),
+    "rev-list" => RevList,
+    "rev-parse" => RevParse,
+    "revert" => Revert,
+    "revisions" => Revisions,
+    "rm" => Rm,
+    "scalar" => Scalar,
+    "send-email" => SendEmail,
+    "send-pack" => SendPack,
+    "sh-i18n" => ShI18n,
+    "sh-setup" => ShSetup,
+    "shortlog" => Shortlog,
+    "show" => Show(GitLog::Normal, GitNames::default()),
+    "show-branch" => ShowBranch,
+    "show-index" => ShowIndex,
+    "show-ref" => ShowRef,
+    "sparse-checkout" => SparseCheckout,
+    "stash" => Stash(GitStash::default()),
+    "stripspace" => Stripspace,
+    "submodule" => Submodule,
+    "svn" => Svn,
+    "switch" => Switch,
+    "symbolic-ref" => SymbolicRef,
+    "tag" => Tag(GitRefs::default()),
+    "unpack-file" => UnpackFile,
+    "unpack-objects" => UnpackObjects,
+    "update-index" => UpdateIndex,
+    "update-ref" => UpdateRef,
+    "update-server-info" => UpdateServerInfo,
+    "var" => Var,
+    "verify-commit" => VerifyCommit,
+    "verify-pack" => VerifyPack,
+    "verify-tag" => VerifyTag,
+    "whatchanged" => WhatChanged,
+    "worktree" => Worktree,
+    "write-tree" => WriteTree,
+    "version" => Version,
 }
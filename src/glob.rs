@@ -0,0 +1,100 @@
+/// Matches `path` against a shell-style glob `pattern`.
+///
+/// * `*` matches anything within a path component
+/// * `**` matches anything, across path components
+/// * `?` matches a single character, except `/`
+/// * `[abc]`, `[a-z]` and `[!a]` match a single character in (or not
+///   in) the set
+/// * `\` escapes the character after it
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let path = path.chars().collect::<Vec<_>>();
+    glob(&pattern, &path)
+}
+
+fn glob(p: &[char], s: &[char]) -> bool {
+    match p {
+        [] => s.is_empty(),
+        ['*', '*', '/', rest @ ..] => {
+            glob(rest, s)
+                || (0..s.len()).any(|i| s[i] == '/' && glob(rest, &s[i + 1..]))
+        }
+        ['*', '*', rest @ ..] => (0..=s.len()).any(|i| glob(rest, &s[i..])),
+        ['*', rest @ ..] => (0..=s.len())
+            .take_while(|&i| i == 0 || s[i - 1] != '/')
+            .any(|i| glob(rest, &s[i..])),
+        ['?', rest @ ..] => match s {
+            [c, s @ ..] if *c != '/' => glob(rest, s),
+            _ => false,
+        },
+        ['[', rest @ ..] => match (class(rest), s) {
+            (Some((set, rest)), [c, s @ ..]) => {
+                *c != '/' && in_class(set, *c) && glob(rest, s)
+            }
+            (Some(_), []) => false,
+            (None, _) => literal('[', rest, s),
+        },
+        ['\\', c, rest @ ..] => literal(*c, rest, s),
+        [c, rest @ ..] => literal(*c, rest, s),
+    }
+}
+
+fn literal(c: char, rest: &[char], s: &[char]) -> bool {
+    matches!(s, [v, s @ ..] if *v == c && glob(rest, s))
+}
+
+/// Splits the pattern right after an opening `[` into the contents of
+/// the character class and everything after its closing `]`.
+fn class(p: &[char]) -> Option<(&[char], &[char])> {
+    let start = match p {
+        ['!' | '^', ']', ..] => 2,
+        ['!' | '^', ..] | [']', ..] => 1,
+        _ => 0,
+    };
+    let end = start + p[start..].iter().position(|c| *c == ']')?;
+    Some((&p[..end], &p[end + 1..]))
+}
+
+fn in_class(set: &[char], c: char) -> bool {
+    let (negate, mut set) = match set {
+        ['!' | '^', set @ ..] => (true, set),
+        set => (false, set),
+    };
+    let mut found = false;
+    while let Some((a, rest)) = set.split_first() {
+        set = match rest {
+            ['-', b, rest @ ..] => {
+                found |= (*a..=*b).contains(&c);
+                rest
+            }
+            rest => {
+                found |= *a == c;
+                rest
+            }
+        };
+    }
+    found != negate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::matches;
+
+    #[test]
+    fn test_glob() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(!matches("*.rs", "src/main.rs"));
+        assert!(matches("src/*", "src/main.rs"));
+        assert!(matches("src/**", "src/core/main.rs"));
+        assert!(matches("src/**", "src/"));
+        assert!(matches("**/*.rs", "main.rs"));
+        assert!(matches("**/*.rs", "src/core/main.rs"));
+        assert!(matches("?.md", "A.md"));
+        assert!(!matches("?.md", "AB.md"));
+        assert!(matches("[a-c].txt", "b.txt"));
+        assert!(!matches("[!a-c].txt", "b.txt"));
+        assert!(matches("[].txt", "[].txt"));
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "a"));
+    }
+}
@@ -0,0 +1,162 @@
+use crate::cache::{Entry, Kind};
+use crate::git::{self, Dirs};
+use crate::indexer::Indexer;
+use crate::prelude::*;
+use crate::status::{uncolor, unquote};
+
+use std::cmp::Reverse;
+use std::path::{Path, PathBuf};
+
+/// Numbers the matches listed by `git grep`. Matches are cached with
+/// their line when it is shown, with `-n` or `grep.lineNumber`.
+///
+/// Example:
+/// ```text
+/// src/main.rs:12:fn main() {      (match)
+/// src/main.rs-13-    let x = 1;   (context, with `-C`)
+/// --                              (between groups of context)
+/// HEAD:src/main.rs:12:fn main() { (match in a tree)
+/// src/main.rs                     (file name, with `--heading` or `-l`)
+/// 12:fn main() {                  (match, with `--heading`)
+/// ```
+pub(crate) struct Grep {
+    git_grep: GitGrep,
+    /// The directory that listed paths are relative to.
+    dir: PathBuf,
+    /// Prefixes of the file names found in trees, such as `HEAD:`.
+    trees: Vec<String>,
+    /// The file named by the last heading, with `--heading`.
+    heading: Option<String>,
+}
+
+impl Grep {
+    pub fn new(git_grep: &GitGrep, dirs: &Dirs) -> Self {
+        let dir = match git_grep.full_name {
+            true => &dirs.toplevel,
+            false => &dirs.base,
+        };
+        let trees = git_grep.revs.iter();
+        let trees =
+            trees.filter(|v| git::is_tree(&dirs.base, &dirs.git_dir, v));
+        let mut trees: Vec<String> = trees.map(|v| format!("{v}:")).collect();
+        // `HEAD~1:` before `HEAD:`
+        trees.sort_by_key(|v| Reverse(v.len()));
+        let (git_grep, dir) = (git_grep.clone(), dir.to_path_buf());
+        Self { git_grep, dir, trees, heading: None }
+    }
+
+    /// Decodes a file name, which may be quoted, and found in a tree.
+    fn path(&self, name: &str) -> String {
+        let tree = self.trees.iter().find_map(|v| name.strip_prefix(v));
+        unquote(tree.unwrap_or(name))
+    }
+
+    /// Checks if `name`, which may be quoted, names a file.
+    fn is_file(&self, name: &str) -> bool {
+        !name.is_empty() && self.dir.join(self.path(name)).is_file()
+    }
+
+    /// Splits a line into a file name, the character that follows it,
+    /// and the rest of the line.
+    ///
+    /// Since file names may contain any of the separators, the name ends
+    /// at a separator that is followed by a line number and the same
+    /// separator again (only `:`, unless lines around matches are shown).
+    /// Where there is more than one of those (as in `a-1-b-2-x`), or no
+    /// line numbers at all, the first prefix that names a file is taken,
+    /// and failing that, the first one that ends at a `:`.
+    fn split<'a>(&self, line: &'a str) -> Option<(&'a str, char, &'a str)> {
+        let numbered = self.git_grep.line_number;
+        let seps = if self.git_grep.context { ":-=" } else { ":" };
+        // the separator in a tree's prefix, as in `HEAD:`, isn't one
+        let tree = self.trees.iter().find(|v| line.starts_with(v.as_str()));
+        let start = tree.map_or(0, |v| v.len());
+        let seps = line.char_indices().filter(|(i, c)| {
+            *i >= start
+                && seps.contains(*c)
+                && (!numbered
+                    || *i > 0 && line_number(&line[i + 1..], *c).is_some())
+        });
+        let seps: Vec<_> = seps.collect();
+        let found = match seps[..] {
+            [v] if numbered => Some(v),
+            _ => seps.iter().copied().find(|(i, _)| self.is_file(&line[..*i])),
+        };
+        // names found in trees, or only in the index, aren't files
+        let found = found.or_else(|| {
+            seps.iter().copied().find(|(_, c)| numbered || *c == ':')
+        });
+        found.map(|(i, c)| (&line[..i], c, &line[i + 1..]))
+    }
+}
+
+/// Reads the line number from the start of `rest`, which is followed by
+/// `sep`: `:` for matches, and `-` or `=` for the lines around them.
+fn line_number(rest: &str, sep: char) -> Option<usize> {
+    let (number, _) = rest.split_once(sep)?;
+    match number.bytes().all(|b| b.is_ascii_digit()) {
+        true => number.parse().ok(),
+        false => None,
+    }
+}
+
+impl Indexer for Grep {
+    fn kind(&self) -> Kind {
+        Kind::Files
+    }
+
+    /// Cached paths are relative to the directory on the first line.
+    fn header(&self) -> Option<&Path> {
+        Some(&self.dir)
+    }
+
+    fn index(&mut self, line: &str) -> Option<String> {
+        let line = String::from_utf8_lossy(&uncolor(line)).to_string();
+        if line == "--" {
+            return None;
+        }
+        let numbered = self.git_grep.line_number;
+        let (path, line) = if self.git_grep.names_only {
+            // `--count` follows each file name with `:<count>`
+            match self.git_grep.count {
+                true => (self.path(line.rsplit_once(':')?.0), None),
+                false => (self.path(&line), None),
+            }
+        } else if self.git_grep.heading {
+            // with line numbers, every other line is a file name
+            let is_file = match numbered {
+                true => {
+                    !line.is_empty()
+                        && ":-="
+                            .chars()
+                            .all(|c| line_number(&line, c).is_none())
+                }
+                // files in trees are named after them, as in `HEAD:a.rs`
+                false => {
+                    self.trees.iter().any(|v| line.starts_with(v.as_str()))
+                        || self.is_file(&line)
+                }
+            };
+            if is_file {
+                self.heading = Some(self.path(&line));
+                return None;
+            }
+            match numbered {
+                true => (self.heading.clone()?, Some(line_number(&line, ':')?)),
+                false if line.is_empty() => return None,
+                false => (self.heading.clone()?, None),
+            }
+        } else {
+            let (path, sep, rest) = self.split(&line)?;
+            if sep != ':' {
+                return None;
+            }
+            let number = match numbered {
+                true => Some(line_number(rest, ':')?),
+                false => None,
+            };
+            (self.path(path), number)
+        };
+        Some(Entry { path, line, ..Default::default() }.to_string())
+    }
+}
@@ -0,0 +1,162 @@
+use crate::cache::Kind;
+use crate::git::Dirs;
+use crate::prelude::*;
+use crate::{grep, log, paths, refs, stash, status};
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, LineWriter, Write};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+
+/// Numbers the things listed in a git command's output, line by line.
+pub(crate) trait Indexer {
+    /// The kind of values that are numbered.
+    fn kind(&self) -> Kind;
+
+    /// First line of the cache file, if the kind of values has one.
+    fn header(&self) -> Option<&Path> {
+        None
+    }
+
+    /// The value to number this line of git's output with, if any.
+    fn index(&mut self, line: &str) -> Option<String>;
+
+    /// Checks if the output lists everything there is to number, so
+    /// that the cache is emptied when it lists nothing. Otherwise, the
+    /// cache is only replaced once something is numbered.
+    fn is_complete(&self) -> bool {
+        false
+    }
+
+    /// Prints a line of git's output, with its number if it has one.
+    /// The line is printed byte for byte, even if it isn't UTF-8.
+    fn print(
+        &self,
+        out: &mut dyn Write,
+        line: &[u8],
+        number: Option<usize>,
+    ) -> io::Result<()> {
+        match number {
+            Some(n) => write!(out, "{: <3}", n)?,
+            None => write!(out, "   ")?,
+        }
+        out.write_all(line)?;
+        writeln!(out)
+    }
+}
+
+/// Picks the indexer for the output of `git_cmd`, if it lists anything
+/// that `gitnu` numbers. `argh` is the command that will be run.
+pub(crate) fn select(
+    git_cmd: &GitCommand,
+    argh: &Command,
+    dirs: &Dirs,
+) -> Option<Box<dyn Indexer>> {
+    use GitCommand as G;
+    let indexer: Box<dyn Indexer> = match git_cmd {
+        // commits and files can't be told apart from the rest of a
+        // format that `gitnu` doesn't know
+        G::Log(GitLog::Custom, _) | G::Show(GitLog::Custom, _) => return None,
+        G::Status(v) if v.is_numbered() => {
+            Box::new(status::Status::new(v, argh, dirs))
+        }
+        G::Log(_, v) | G::Show(_, v) | G::Diff(v) | G::LsFiles(v)
+            if v.names != Names::None =>
+        {
+            Box::new(paths::Paths::new(git_cmd, dirs))
+        }
+        G::Log(v, _) => Box::new(log::Commits::new(v)),
+        G::Grep(v) if !v.null && !v.no_names => {
+            Box::new(grep::Grep::new(v, dirs))
+        }
+        G::Branch(v) if v.is_list() => Box::new(refs::Refs::Branches),
+        G::Tag(v) if v.is_list() => Box::new(refs::Refs::Tags),
+        G::Stash(v) if v.is("list") => {
+            Box::new(stash::Stashes::new(&dirs.git_dir))
+        }
+        _ => return None,
+    };
+    Some(indexer)
+}
+
+/// Starts `pager` to show the numbered output in, like git would.
+fn spawn_pager(pager: &str, dirs: &Dirs) -> Option<Child> {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", pager]).current_dir(&dirs.base).stdin(Stdio::piped());
+    // git's defaults, which let output that fits on screen through
+    if env::var_os("LESS").is_none() {
+        cmd.env("LESS", "FRX");
+    }
+    if env::var_os("LV").is_none() {
+        cmd.env("LV", "-c");
+    }
+    cmd.spawn().ok()
+}
+
+/// Runs `argh`, printing its output numbered by `indexer`, and caches
+/// the numbered values. The output is shown in `pager` if there is one.
+pub(crate) fn run(
+    mut argh: Command,
+    dirs: &Dirs,
+    indexer: &mut dyn Indexer,
+    pager: Option<String>,
+) -> Result<ExitStatus> {
+    let mut git = argh.stdout(Stdio::piped()).spawn()?;
+
+    let lines = match git.stdout.take() {
+        Some(v) => BufReader::new(v).split(b'\n').map_while(|v| v.ok()),
+        None => return Ok(git.wait()?),
+    };
+
+    let mut writer = None;
+    let cache = |indexer: &dyn Indexer| -> Result<LineWriter<File>> {
+        let mut writer = indexer.kind().writer(&dirs.git_dir, &dirs.base)?;
+        if let Some(header) = indexer.header() {
+            writeln!(writer, "{}", header.display()).unwrap();
+        }
+        Ok(writer)
+    };
+    if indexer.is_complete() {
+        writer = Some(cache(indexer)?);
+    }
+
+    let mut pager = pager.and_then(|v| spawn_pager(&v, dirs));
+    let mut out: Box<dyn Write> = match pager.as_mut() {
+        Some(v) => Box::new(BufWriter::new(v.stdin.take().unwrap())),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    let mut count = 1;
+    for line in lines {
+        // lines that aren't UTF-8, such as diffs of Latin-1 files, are
+        // only read lossily, and still printed as they are
+        let value = indexer.index(&String::from_utf8_lossy(&line));
+        // every value is cached, but only the first few are numbered
+        let number = value.as_ref().map(|_| count);
+        let number = number.filter(|n| *n <= MAX_CACHE_SIZE);
+        // stop reading once the pager is closed
+        if indexer.print(&mut out, &line, number).is_err() {
+            break;
+        }
+        if let Some(value) = value {
+            if writer.is_none() {
+                writer = Some(cache(indexer)?);
+            }
+            writeln!(writer.as_mut().unwrap(), "{value}").unwrap();
+            count += 1;
+        }
+    }
+
+    // close the writer, and the pager's input
+    if let Some(mut writer) = writer {
+        writer.flush().ok();
+    }
+    out.flush().ok();
+    drop(out);
+    if let Some(mut pager) = pager {
+        pager.wait().ok();
+    }
+
+    Ok(git.wait()?)
+}
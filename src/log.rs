@@ -0,0 +1,69 @@
+use crate::cache::Kind;
+use crate::indexer::Indexer;
+use crate::prelude::*;
+use crate::status::uncolor;
+
+use std::io::{self, Write};
+
+/// Characters that `git log --graph` draws to the left of a commit.
+const GRAPH: &[char] = &['*', '|', '/', '\\', '_', ' '];
+
+/// Checks if `word` looks like a (possibly abbreviated) commit hash.
+fn is_hash(word: &str) -> bool {
+    word.len() >= 7 && word.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Gets the commit hash that starts this line of `git log`, if any.
+///
+/// Example:
+/// ```text
+/// * 3e1b5c0 (HEAD -> main) Fix typo     (--oneline)
+/// * commit 3e1b5c0f9d...                (normal)
+/// ```
+pub fn hash(git_log: &GitLog, line: &str) -> Option<String> {
+    let line = String::from_utf8_lossy(&uncolor(line)).to_string();
+    let line = line.trim_start_matches(GRAPH);
+    let line = match git_log {
+        GitLog::Oneline => line,
+        GitLog::Normal => line.strip_prefix("commit ")?,
+        GitLog::Custom => return None,
+    };
+    let word = line.split_whitespace().next()?;
+    is_hash(word).then(|| word.to_string())
+}
+
+/// Numbers the commits listed by `git log`.
+pub(crate) struct Commits {
+    git_log: GitLog,
+}
+
+impl Commits {
+    pub fn new(git_log: &GitLog) -> Self {
+        Self { git_log: git_log.clone() }
+    }
+}
+
+impl Indexer for Commits {
+    fn kind(&self) -> Kind {
+        Kind::Commits
+    }
+
+    fn index(&mut self, line: &str) -> Option<String> {
+        hash(&self.git_log, line)
+    }
+
+    /// Lines other than commits, such as diffs with `-p`, are printed
+    /// just as git prints them.
+    fn print(
+        &self,
+        out: &mut dyn Write,
+        line: &[u8],
+        number: Option<usize>,
+    ) -> io::Result<()> {
+        if let Some(n) = number {
+            write!(out, "{: <3}", n)?;
+        }
+        out.write_all(line)?;
+        writeln!(out)
+    }
+}
@@ -1,28 +1,68 @@
 mod cache;
+mod completions;
+mod config;
 mod error;
 mod git;
 mod git_cmd;
+mod glob;
+mod grep;
+mod indexer;
+mod log;
+mod native;
 mod parse;
 mod pathdiff;
+mod paths;
 mod prelude;
+mod refs;
+mod stash;
 mod status;
 
 #[cfg(test)]
 mod tests;
 
+use config::Paging;
+use git::Dirs;
+use native::Native;
 use prelude::*;
 
 use std::env::{args, current_dir};
+use std::io::{stdout, IsTerminal};
 use std::path::PathBuf;
 use std::process::{Command, ExitCode, ExitStatus};
 
 /// Returning `Err` here means the failure comes from factors outside
 /// of `gitnu`. This means we should execute a full bypass to `git` to
 /// let it reflect the errors.
-fn prefetch(cwd: PathBuf) -> Result<(PathBuf, PathBuf, Aliases)> {
-    let git_dir = git::dir(&cwd)?;
-    let git_aliases = git::aliases();
-    Ok((cwd, git_dir, git_aliases))
+///
+/// Git's own options in `args` (such as `-C` and `--git-dir`) decide
+/// which repository is used, and the directory that git resolves paths
+/// from.
+fn prefetch(cwd: PathBuf, args: &[String]) -> Result<(Dirs, Config)> {
+    let (dirs, globals) = parse::globals(args);
+    let cwd = dirs.iter().fold(cwd, |cwd, dir| cwd.join(dir));
+    let dirs = git::dirs(&cwd, &globals)?;
+    let config = git::config(&cwd, &globals);
+    Ok((dirs, config))
+}
+
+/// The pager that git would show the output of a command in, given the
+/// name it is configured by and whether git pages it by default.
+fn pager(
+    (name, default): (&str, bool),
+    config: &Config,
+    dirs: &Dirs,
+    globals: &[&str],
+) -> Option<String> {
+    let (paged, program) = match config.pagers.get(name) {
+        Some(Paging::Enabled(v)) => (*v, None),
+        Some(Paging::With(v)) => (true, Some(v.as_str())),
+        None => (default, None),
+    };
+    let paged = (paged
+        || globals.iter().any(|v| matches!(*v, "--paginate" | "-p")))
+        && !globals.iter().any(|v| matches!(*v, "--no-pager" | "-P"))
+        && stdout().is_terminal();
+    paged.then(|| git::pager(&dirs.base, globals, program)).flatten()
 }
 
 /// Return status here does NOT depend on `gitnu` logic. It's purely
@@ -30,13 +70,24 @@ fn prefetch(cwd: PathBuf) -> Result<(PathBuf, PathBuf, Aliases)> {
 fn postrun(
     mut cmd: Command,
     git_cmd: Option<GitCommand>,
-    git_dir: PathBuf,
+    config: &Config,
+    dirs: Dirs,
+    globals: &[&str],
 ) -> Result<ExitStatus> {
     use GitCommand as G;
+    // Commands that list things that `gitnu` numbers, which requires
+    // __writing__ to the cache.
+    let indexer =
+        git_cmd.as_ref().and_then(|v| indexer::select(v, &cmd, &dirs));
+    if let Some(mut indexer) = indexer {
+        // git can't page what it doesn't print itself, so `gitnu` does
+        let pager = git_cmd.as_ref().and_then(|v| v.pager());
+        let pager = pager.and_then(|v| self::pager(v, config, &dirs, globals));
+        return indexer::run(cmd, &dirs, indexer.as_mut(), pager);
+    }
     match git_cmd {
-        // Special case for `git nu status` because that requires
-        // __writing__ to the cache.
-        Some(v @ G::Status(_)) => status::git_status(cmd, &git_dir, v),
+        // `git nu stash` checks the stashes it numbered before use.
+        Some(G::Stash(v)) => stash::git_stash(cmd, &dirs, v),
         // For `git version`, append `gitnu`'s version below.
         Some(G::Version) => {
             let result = cmd.run();
@@ -48,26 +99,51 @@ fn postrun(
     }
 }
 
+/// Runs `args` without a repository to number things in. Subcommands
+/// that `gitnu` runs on its own do so without a cache, except for `ls`,
+/// which has nothing to list. Anything else is a full bypass to `git`.
+fn bypass(cwd: PathBuf, args: &[String]) -> Result<ExitStatus> {
+    let (dirs, globals) = parse::globals(args);
+    let cwd = dirs.iter().fold(cwd, |cwd, dir| cwd.join(dir));
+    let config = git::config(&cwd, &globals);
+    let cache = Cache::empty(&cwd);
+    match parse::native(args, &config, &cache) {
+        Some((Native::Ls, _)) => {
+            eprintln!("fatal: not a git repository");
+            error!(NotGitRepository)
+        }
+        Some((native, native_args)) => {
+            let dirs =
+                Dirs { base: cwd.clone(), toplevel: cwd, ..Default::default() };
+            native.run(native_args, &cache, &dirs, &globals)
+        }
+        None => {
+            let mut git = Command::new("git");
+            git.args(&args[1..]);
+            git.status().map_err(Error::from)
+        }
+    }
+}
+
 /// A complete run from `cwd` and `args` to the end. Suitable for
 /// running `gitnu` entirely during functional tests.
 fn main_cli(cwd: PathBuf, args: &[String]) -> Result<ExitStatus> {
-    let (cwd, git_dir, git_aliases) = match prefetch(cwd) {
+    let (dirs, config) = match prefetch(cwd.clone(), args) {
         Ok(v) => v,
-        Err(_) => {
-            // Run a full bypass
-            let mut git = Command::new("git");
-            git.args(&args[1..]);
-            return git.status().map_err(Error::from);
-        }
+        Err(_) => return bypass(cwd, args),
     };
 
     let mut argh = Command::new("git");
     argh.current_dir(&cwd);
 
-    let cache = Cache::new(&git_dir, &cwd);
-    let (argh, git_cmd) = parse::parse(&args, git_aliases, cache, argh);
+    let cache = Cache::new(&dirs.git_dir, &dirs.base);
+    let (_, globals) = parse::globals(args);
+    if let Some((native, native_args)) = parse::native(args, &config, &cache) {
+        return native.run(native_args, &cache, &dirs, &globals);
+    }
+    let (argh, git_cmd) = parse::parse(args, &config, cache, argh);
 
-    postrun(argh, git_cmd, git_dir)
+    postrun(argh, git_cmd, &config, dirs, &globals)
 }
 
 fn main() -> ExitCode {
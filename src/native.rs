@@ -0,0 +1,207 @@
+use crate::cache::Kind;
+use crate::completions;
+use crate::error;
+use crate::git::{self, Dirs};
+use crate::parse::Arg;
+use crate::prelude::*;
+
+use std::process::{Command, ExitStatus};
+
+/// Subcommands that `gitnu` runs on its own, rather than passing them
+/// to git. Git aliases of the same name take precedence over these.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum Native {
+    /// `git nu edit`: opens files in git's editor.
+    Edit,
+    /// `git nu ls`: prints the numbered files again.
+    Ls,
+    /// `git nu which`: prints the files that numbers refer to, or the
+    /// other way around.
+    Which,
+    /// `git nu completions <shell>`: prints a shell completion script.
+    Completions,
+    /// `git nu exec <program>`, or `git nu -x <program>`: runs any
+    /// program, not just git.
+    Exec,
+}
+
+/// Names of all native subcommands.
+pub const NATIVES: &[&str] = &["edit", "ls", "which", "completions", "exec"];
+
+impl Native {
+    pub fn from_arg(arg: &str) -> Option<Self> {
+        match arg {
+            "edit" => Some(Self::Edit),
+            "ls" => Some(Self::Ls),
+            "which" => Some(Self::Which),
+            "completions" => Some(Self::Completions),
+            "exec" | "-x" => Some(Self::Exec),
+            _ => None,
+        }
+    }
+
+    /// Runs the subcommand with its already-expanded arguments.
+    pub fn run(
+        &self,
+        args: Vec<Arg>,
+        cache: &Cache,
+        dirs: &Dirs,
+        globals: &[&str],
+    ) -> Result<ExitStatus> {
+        match self {
+            Self::Edit => edit(args, cache, dirs, globals),
+            Self::Ls => ls(args, cache),
+            Self::Which => which(args, cache),
+            Self::Completions => completions(args),
+            Self::Exec => exec(args, cache, dirs),
+        }
+    }
+}
+
+/// Opens files in the editor that git would use. Files from `git nu
+/// grep` are opened at the line of their match.
+fn edit(
+    args: Vec<Arg>,
+    cache: &Cache,
+    dirs: &Dirs,
+    globals: &[&str],
+) -> Result<ExitStatus> {
+    let editor = git::editor(&dirs.base, globals)?;
+    let mut files = vec![];
+    for arg in args {
+        match arg {
+            Arg::Literal(v) => files.push(v.to_string()),
+            Arg::Index(kind, i) => {
+                let entry = cache.entry(i).filter(|_| kind == Kind::Files);
+                if let Some(line) = entry.and_then(|v| v.line) {
+                    files.push(format!("+{line}"));
+                }
+                cache.load(kind, i, &mut files);
+            }
+        }
+    }
+    // Like git, run the editor through the shell, since it may come with
+    // arguments of its own.
+    let mut sh = Command::new("sh");
+    sh.current_dir(&dirs.base).arg("-c").arg(format!("{editor} \"$@\""));
+    Ok(sh.arg(&editor).args(files).status()?)
+}
+
+/// Prints the cached files, as numbered by the last listing, relative
+/// to the current directory. Prints only the selected files, if any
+/// are given.
+fn ls(args: Vec<Arg>, cache: &Cache) -> Result<ExitStatus> {
+    let indices: Vec<usize> = match args.is_empty() {
+        true => (1..=cache.len(Kind::Files)).collect(),
+        false => args
+            .into_iter()
+            .filter_map(|v| match v {
+                Arg::Index(Kind::Files, i) => Some(i),
+                _ => None,
+            })
+            .collect(),
+    };
+    for i in indices {
+        let (Some(entry), Some(path)) =
+            (cache.entry(i), cache.get(Kind::Files, i))
+        else {
+            continue;
+        };
+        let path = match (entry.line, cache.orig(i)) {
+            (Some(line), _) => format!("{path}:{line}"),
+            (None, Some(orig)) => format!("{orig} -> {path}"),
+            (None, None) => path,
+        };
+        match entry.xy.is_empty() {
+            true => println!("{: <3}{path}", i),
+            false => println!("{: <3}{} {path}", i, entry.xy.replace('.', " ")),
+        }
+    }
+    Ok(ExitStatus::default())
+}
+
+/// Prints the values that numbers refer to, one per line, for use in
+/// scripts. These are exactly what `git nu` would pass to git.
+///
+/// Options:
+///   * `-z`, `--null`: end each value with NUL instead of a newline.
+///   * `--index`: print the numbers of the files given instead.
+fn which(args: Vec<Arg>, cache: &Cache) -> Result<ExitStatus> {
+    let (mut end, mut index, mut values) = ('\n', false, vec![]);
+    for arg in args {
+        match arg {
+            Arg::Literal(v) if v == "-z" || v == "--null" => end = '\0',
+            Arg::Literal(v) if v == "--index" => index = true,
+            v => values.push(v),
+        }
+    }
+    let mut missing = false;
+    for arg in values {
+        let value = match (arg, index) {
+            (Arg::Index(kind, i), false) => {
+                cache.get(kind, i).ok_or(i.to_string())
+            }
+            (Arg::Literal(v), false) => Ok(v.to_string()),
+            (Arg::Index(kind, i), true) => match i <= cache.len(kind) {
+                true => Ok(i.to_string()),
+                false => Err(i.to_string()),
+            },
+            (Arg::Literal(v), true) => {
+                cache.find(&v).map(|i| i.to_string()).ok_or(v.to_string())
+            }
+        };
+        match value {
+            Ok(v) => print!("{v}{end}"),
+            Err(v) => {
+                eprintln!("error: `{v}` is not in the cache");
+                missing = true;
+            }
+        }
+    }
+    match missing {
+        true => error!(NotCached),
+        false => Ok(ExitStatus::default()),
+    }
+}
+
+/// Prints the completion script for the shell given.
+fn completions(args: Vec<Arg>) -> Result<ExitStatus> {
+    let shell = match args.first() {
+        Some(Arg::Literal(v)) => v.as_ref(),
+        _ => "",
+    };
+    let commands = [NATIVES, COMMANDS].concat();
+    match completions::script(shell, &commands) {
+        Some(v) => print!("{v}"),
+        None => {
+            eprintln!("usage: git nu completions (bash | zsh | fish)");
+            return error!(InvalidArgument);
+        }
+    }
+    Ok(ExitStatus::default())
+}
+
+/// Runs a program with numbers in its arguments expanded, from the
+/// directory that git would run from.
+fn exec(args: Vec<Arg>, cache: &Cache, dirs: &Dirs) -> Result<ExitStatus> {
+    let mut args = args.into_iter();
+    let program = match args.next() {
+        Some(Arg::Literal(v)) => v.to_string(),
+        _ => {
+            eprintln!("usage: git nu exec <program> [<args>...]");
+            return error!(InvalidArgument);
+        }
+    };
+    let mut cmd = Command::new(&program);
+    cmd.current_dir(&dirs.base);
+    for arg in args {
+        match arg {
+            Arg::Literal(v) => cmd.add_arg(v.as_ref()),
+            Arg::Index(kind, i) => cache.load(kind, i, &mut cmd),
+        }
+    }
+    cmd.status().map_err(|e| {
+        eprintln!("error: cannot run `{program}`: {e}");
+        Error::from(e)
+    })
+}
@@ -1,84 +1,413 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::HashSet;
 #[cfg(not(test))]
 use std::io::IsTerminal;
 
+use crate::cache::{Category, Kind};
+use crate::config::Collision;
+use crate::native::Native;
 use crate::prelude::*;
 
-type Aliases = HashMap<String, String>;
+/// Parses a single index, where `len` is the number of cached files.
+/// Indices prefixed with `~` count from the end of the cache.
+/// "5"  -> Some(5)
+/// "~1" -> Some(len)
+/// "~2" -> Some(len - 1)
+fn parse_index(arg: &str, len: usize) -> Option<usize> {
+    match arg.strip_prefix('~') {
+        Some(v) => match v.parse::<usize>() {
+            Ok(v) if v > 0 && v <= len => Some(len + 1 - v),
+            _ => None,
+        },
+        None => arg.parse::<usize>().ok(),
+    }
+}
 
-/// Parses a string into an inclusive range.
+/// Parses a string into an inclusive range, where `len` is the number
+/// of cached files. Open-ended ranges that select nothing are `None`, so
+/// that they are passed on as they are.
 /// "5"   -> Some([5, 5])
 /// "2-6" -> Some([2, 6])
+/// "5-"  -> Some([5, len])
+/// "~3-" -> Some([len - 2, len])
+/// "all" -> Some([1, len])
 /// "foo" -> None
-pub fn parse_range(arg: &str) -> Option<(usize, usize)> {
-    if let Ok(single) = arg.parse::<usize>() {
+pub fn parse_range(arg: &str, len: usize) -> Option<(usize, usize)> {
+    if arg == "all" {
+        (len > 0).then_some((1, len))
+    } else if let Some(single) = parse_index(arg, len) {
         Some((single, single))
     } else {
         let (a, b) = arg.split_once('-')?;
-        let a = a.parse::<usize>().ok()?;
-        let b = b.parse::<usize>().ok()?;
-        Some((a.min(b), a.max(b)))
+        let a = parse_index(a, len)?;
+        match b {
+            "" => (a <= len).then_some((a, len)),
+            b => parse_index(b, len).map(|b| (a.min(b), a.max(b))),
+        }
+    }
+}
+
+/// Parses a selection: a comma-separated list of ranges and status
+/// categories, or a glob prefixed with `@`, that is either added to or
+/// removed from the final expansion. Removals are prefixed with `^` or
+/// `!`.
+/// "2-6"        -> Some((true, [2, 3, 4, 5, 6]))
+/// "1,3-4"      -> Some((true, [1, 3, 4]))
+/// "^4"         -> Some((false, [4]))
+/// "!4-6,8"     -> Some((false, [4, 5, 6, 8]))
+/// "untracked"  -> Some((true, <indices of untracked files>))
+/// "@*.rs"      -> Some((true, <indices of files ending with .rs>))
+///
+/// Indices refer to cached values of `kind`. Categories only apply
+/// where `categories` is set, which is where only pathspecs can go. The
+/// same goes for removing files, since `^` elsewhere excludes a revision.
+pub fn parse_selection(
+    arg: &str,
+    cache: &Cache,
+    kind: Kind,
+    categories: bool,
+) -> Option<(bool, Vec<usize>)> {
+    let exclusion = arg.strip_prefix(['^', '!']);
+    let (include, arg) = match exclusion {
+        Some(arg) if kind != Kind::Files || categories => (false, arg),
+        _ => (true, arg),
+    };
+    // globs that match nothing are left as-is
+    if let Some(pattern) = arg.strip_prefix('@') {
+        let indices = cache.glob(kind, pattern);
+        return (!indices.is_empty()).then_some((include, indices));
+    }
+    // numbers past the end of the cache, such as the years in
+    // `2020-2024`, are left as-is
+    let max = cache.len(kind).max(MAX_CACHE_SIZE);
+    let mut indices = vec![];
+    for v in arg.split(',') {
+        match parse_range(v, cache.len(kind)) {
+            Some((_, end)) if end > max => return None,
+            Some((start, end)) => indices.extend(start..end + 1),
+            // categories that select nothing are left as-is
+            None if categories => match cache.select(Category::from_arg(v)?) {
+                v if v.is_empty() => return None,
+                v => indices.extend(v),
+            },
+            None => return None,
+        }
+    }
+    Some((include, indices))
+}
+
+/// Checks if `arg` is only made of numbers and ranges, such as `1,3-5`,
+/// `4-` or `~2`.
+fn is_numeric(arg: &str) -> bool {
+    let is_numeric = |c: char| c.is_ascii_digit() || c == '-' || c == '~';
+    arg.split(',').all(|v| !v.is_empty() && v.chars().all(is_numeric))
+}
+
+/// Expands a revision range between cached commits, such as `1..4`,
+/// `2...` or `..~1`.
+fn parse_commit_range(arg: &str, cache: &Cache) -> Option<String> {
+    let dots = if arg.contains("...") { "..." } else { ".." };
+    let (a, b) = arg.split_once(dots)?;
+    let len = cache.len(Kind::Commits);
+    let get = |v: &str| match v {
+        "" => Some(String::new()),
+        v => cache.get(Kind::Commits, parse_index(v, len)?),
+    };
+    match (get(a)?, get(b)?) {
+        (a, b) if a.is_empty() && b.is_empty() => None,
+        (a, b) => Some(format!("{a}{dots}{b}")),
+    }
+}
+
+/// Git's own options that take the next argument as their value.
+#[rustfmt::skip]
+const GLOBAL_VALUE_OPTIONS: [&str; 8] = [
+    "-C", "-c", "--git-dir", "--work-tree", "--namespace", "--super-prefix",
+    "--config-env", "--attr-source",
+];
+
+/// Number of leading `args` that are git's own options, such as
+/// `-C <path>` or `--git-dir=<path>`. These always come before the
+/// git command.
+fn globals_len(args: &[String]) -> usize {
+    let mut i = 0;
+    while let Some(arg) = args.get(i) {
+        // `--version` and the like are commands in disguise
+        if !arg.starts_with('-')
+            || GitCommand::try_from(arg).is_ok()
+            || Native::from_arg(arg).is_some()
+        {
+            break;
+        }
+        i += 1 + GLOBAL_VALUE_OPTIONS.contains(&arg.as_str()) as usize;
+    }
+    i.min(args.len())
+}
+
+/// Splits git's own options into the directories given by `-C`, and
+/// everything else. Takes ALL args, including the bin path.
+pub fn globals(args: &[String]) -> (Vec<&str>, Vec<&str>) {
+    let args = args.get(1..).unwrap_or_default();
+    let mut globals = args[..globals_len(args)].iter().map(|v| v.as_str());
+    let (mut dirs, mut rest) = (vec![], vec![]);
+    while let Some(arg) = globals.next() {
+        match arg {
+            "-C" => dirs.extend(globals.next()),
+            _ => rest.push(arg),
+        }
     }
+    (dirs, rest)
+}
+
+/// Splits the `:old` or `:new` suffix off of a selection of files,
+/// which picks one path of those that were renamed or copied. `true`
+/// picks the original path.
+fn side(arg: &str) -> Option<(&str, bool)> {
+    match arg.rsplit_once(':')? {
+        (v, "old") => Some((v, true)),
+        (v, "new") => Some((v, false)),
+        _ => None,
+    }
+}
+
+/// An argument after the git command, before cache expansion.
+pub(crate) enum Arg<'a> {
+    Literal(Cow<'a, str>),
+    Index(Kind, usize),
+}
+
+/// Expands the arguments after the command (and any arguments implied
+/// by an alias) into literals and cached values, reading the command's
+/// options along the way.
+///
+/// Exclusions apply to the whole command, and each index is only
+/// expanded the first time it is seen on either side of `--`.
+fn expand<'a>(
+    args: &'a [String],
+    implied: &'a [String],
+    git_cmd: &mut GitCommand,
+    config: &Config,
+    cache: &Cache,
+) -> Vec<Arg<'a>> {
+    let (mut parsed, mut excluded) = (vec![], HashSet::new());
+    let (mut is_value, mut dashdash) = (false, false);
+    // Arguments implied by an alias are read, but not added, since git
+    // expands the alias on its own.
+    for (i, arg) in implied.iter().chain(args).enumerate() {
+        let arg = arg.as_str();
+        match git_cmd {
+            GitCommand::Status(v) if !dashdash => v.read(arg),
+            GitCommand::Log(v, n) | GitCommand::Show(v, n) => {
+                v.read(arg);
+                n.read(arg)
+            }
+            GitCommand::Diff(n) | GitCommand::LsFiles(n) => n.read(arg),
+            GitCommand::Grep(v) if !dashdash => v.read(arg),
+            GitCommand::Restore(v) if !dashdash => v.read(arg),
+            _ => {}
+        }
+        // option values are never expanded
+        let skip = std::mem::take(&mut is_value);
+        if !skip && !dashdash {
+            is_value = git_cmd.takes_value(arg);
+            dashdash = arg == "--";
+        }
+        // the pattern and trees given to `git grep`, before pathspecs
+        if let GitCommand::Grep(v) = git_cmd {
+            if !skip && !dashdash && !arg.starts_with('-') {
+                v.positional(arg);
+            }
+        }
+        // the first argument to `git stash` that isn't an option
+        if let GitCommand::Stash(v) = git_cmd {
+            if !skip && !dashdash && !arg.starts_with('-') {
+                v.subcommand(arg);
+            }
+        }
+        // `git branch` and `git tag` list refs unless given refs to act on
+        if let GitCommand::Branch(v) | GitCommand::Tag(v) = git_cmd {
+            match arg {
+                "--list" | "-l" => v.list(),
+                "-u" | "--unset-upstream" | "--edit-description"
+                | "--show-current" => v.edit(),
+                _ if arg.starts_with("--set-upstream-to") => v.edit(),
+                _ if !skip && (dashdash || !arg.starts_with('-')) => v.edit(),
+                _ => {}
+            }
+        }
+        if i < implied.len() {
+            continue;
+        }
+        // pathspecs always come after `--`
+        let kind = if dashdash { Kind::Files } else { git_cmd.kind() };
+        // Status categories are words that could just as well name a
+        // ref, so they are only expanded where a ref can't go.
+        let categories =
+            kind == Kind::Files && (dashdash || git_cmd.takes_only_paths());
+        if let Some(v) = (!skip && !dashdash)
+            .then(|| parse_commit_range(arg, cache))
+            .flatten()
+        {
+            parsed.push(Arg::Literal(v.into()));
+            continue;
+        }
+        // one path of a rename, as in `3:old`
+        let side = side(arg).filter(|_| kind == Kind::Files);
+        // Other programs only get numbers and ranges expanded, since words
+        // such as `all` or `M` mean something else to them.
+        let selects = |arg: &str| {
+            !skip && (*git_cmd != GitCommand::Shell || is_numeric(arg))
+        };
+        let selection = side.map_or(arg, |v| v.0);
+        let selection = selects(selection)
+            .then(|| parse_selection(selection, cache, kind, categories))
+            .flatten();
+        // Arguments that name existing files are subject to
+        // `gitnu.collision`.
+        let selection = match selection {
+            Some(_) if cache.exists(arg) => match config.collision {
+                Collision::Path => None,
+                Collision::Warn => {
+                    eprintln!(
+                        "warning: expanded `{arg}`, which is also a file"
+                    );
+                    selection
+                }
+            },
+            v => v,
+        };
+        match selection {
+            Some((true, indices)) => {
+                let arg = |i| match side {
+                    // just the one path, even for commands that unstage
+                    Some((_, old)) => {
+                        let orig = old.then(|| cache.orig(i)).flatten();
+                        let path = orig.or_else(|| cache.get(kind, i));
+                        Arg::Literal(path.unwrap_or(i.to_string()).into())
+                    }
+                    None => Arg::Index(kind, i),
+                };
+                parsed.extend(indices.into_iter().map(arg))
+            }
+            Some((false, indices)) => {
+                excluded.extend(indices.into_iter().map(|i| (kind, i)))
+            }
+            None => match arg.strip_prefix('\\') {
+                // `\` escapes anything that would otherwise be expanded
+                Some(v)
+                    if selects(v)
+                        && parse_selection(v, cache, kind, categories)
+                            .is_some() =>
+                {
+                    parsed.push(Arg::Literal(v.into()))
+                }
+                _ => parsed.push(Arg::Literal(arg.into())),
+            },
+        }
+    }
+
+    // pathspecs after `--` are expanded apart from what comes before
+    let mut seen = HashSet::new();
+    let parsed = parsed.into_iter().filter(|v| match v {
+        Arg::Literal(v) if v == "--" && !seen.contains(&None) => {
+            seen = HashSet::from([None]);
+            true
+        }
+        Arg::Literal(_) => true,
+        Arg::Index(kind, i) => {
+            !excluded.contains(&(*kind, *i)) && seen.insert(Some((*kind, *i)))
+        }
+    });
+    let parsed: Vec<Arg> = parsed.collect();
+    if let GitCommand::Stash(v) = git_cmd {
+        let stashes =
+            parsed.iter().any(|v| matches!(v, Arg::Index(Kind::Stashes, _)));
+        stashes.then(|| v.numbered());
+    }
+    parsed
+}
+
+/// Finds a subcommand that `gitnu` runs on its own, and expands its
+/// arguments, which all refer to files. Takes ALL args, including the
+/// bin path.
+///
+/// A git alias of the same name is left to git instead.
+pub fn native<'a>(
+    args: &'a [String],
+    config: &Config,
+    cache: &Cache,
+) -> Option<(Native, Vec<Arg<'a>>)> {
+    let args = &args[1..]; // skip the binary path
+    let (_, args) = args.split_at(globals_len(args));
+    let name = args.first()?;
+    if config.aliases.contains_key(name) {
+        return None;
+    }
+    let native = Native::from_arg(name)?;
+    // stand-ins for commands without options of their own
+    let mut git_cmd = match native {
+        Native::Exec => GitCommand::Shell,
+        _ => GitCommand::Native,
+    };
+    let args = expand(&args[1..], &[], &mut git_cmd, config, cache);
+    Some((native, args))
 }
 
 /// Parses ALL args, including the bin path.
 pub fn parse<A: ArgHolder>(
     args: &[String],
-    aliases: Aliases,
+    config: &Config,
     cache: Cache,
     mut argh: A,
 ) -> (A, Option<GitCommand>) {
-    let mut git_cmd = None::<GitCommand>;
-
     #[cfg(not(test))]
     if std::io::stdout().is_terminal() {
         argh.add_args(["-c", "color.ui=always"]);
     }
 
-    let mut args = &args[1..]; // skip the binary path
+    let args = &args[1..]; // skip the binary path
 
     // BEFORE git command is found
-    while !args.is_empty() {
-        let arg = args[0].as_str();
-        args = &args[1..];
-        match GitCommand::from_arg(&aliases, arg) {
-            Some(v) => {
-                git_cmd = Some(v);
-                argh.add_arg(arg);
-                break;
-            }
-            _ => argh.add_arg(arg),
-        }
-    }
+    let (globals, args) = args.split_at(globals_len(args));
+    argh.add_args(globals);
+    let found = args.first().and_then(|v| {
+        argh.add_arg(v);
+        GitCommand::from_arg(&config.aliases, v)
+    });
 
     // AFTER git command is looked for/found
-    if let None = git_cmd {
-        // add remaining args and send it
-        argh.add_args(args);
-        return (argh, git_cmd);
+    let args = args.get(1..).unwrap_or_default();
+    let (mut git_cmd, implied) = match found {
+        Some(v) => v,
+        None => {
+            // add remaining args and send it
+            argh.add_args(args);
+            return (argh, None);
+        }
+    };
+
+    match &mut git_cmd {
+        GitCommand::Status(v) => v.configure(&config.status),
+        GitCommand::Grep(v) => {
+            v.line_number = config.grep_line_number;
+            v.full_name = config.grep_full_name;
+        }
+        _ => {}
     }
 
-    for i in 0..args.len() {
-        let arg = args[i].as_str();
-        let git_cmd = git_cmd.as_mut().unwrap();
-        match git_cmd {
-            GitCommand::Status(ref mut v) => match arg {
-                "--short" | "-s" | "--porcelain" => v.short(),
-                _ => {}
-            },
-            _ => {}
-        };
-        let skip = i > 0 && git_cmd.skip_next_arg(&args[i - 1]);
-        match (skip, parse_range(&arg)) {
-            (false, Some((start, end))) if end <= MAX_CACHE_SIZE => {
-                for i in start..end + 1 {
-                    cache.load(i, &mut argh);
+    for arg in expand(args, &implied, &mut git_cmd, config, &cache) {
+        match arg {
+            Arg::Literal(arg) => argh.add_arg(arg.as_ref()),
+            Arg::Index(kind, i) => {
+                cache.load(kind, i, &mut argh);
+                // a rename is only unstaged along with its original path
+                if kind == Kind::Files && git_cmd.unstages() {
+                    argh.add_args(cache.orig(i));
                 }
             }
-            _ => argh.add_arg(arg),
         }
     }
-    (argh, git_cmd)
+    (argh, Some(git_cmd))
 }
 
 #[cfg(test)]
@@ -89,7 +418,7 @@ mod tests {
     fn parse(args: &[&str]) -> Vec<String> {
         let mut args = string_vec(args);
         args.insert(0, "git".to_string());
-        super::parse(&args, Aliases::default(), Cache::default(), vec![]).0
+        super::parse(&args, &Config::default(), Cache::default(), vec![]).0
     }
 
     macro_rules! test {
@@ -107,12 +436,9 @@ mod tests {
     test!(test_range, ["add", "2-4"], ["add", "2", "3", "4"]);
     test!(test_mix, ["add", "8", "2-4"], ["add", "8", "2", "3", "4"]);
 
-    // Gitnu will not seek to interfere with these cases smartly.
-    test!(
-        test_overlap,
-        ["add", "3-5", "2-4"],
-        ["add", "3", "4", "5", "2", "3", "4"]
-    );
+    // Overlapping ranges only expand each index once, in the order
+    // they were first seen.
+    test!(test_overlap, ["add", "3-5", "2-4"], ["add", "3", "4", "5", "2"]);
 
     // anything after `--` will also be processed. This is for commands
     // like `git reset` which requires pathspecs to appear after --.
@@ -121,11 +447,76 @@ mod tests {
         ["add", "3-5", "--", "2-4"],
         ["add", "3", "4", "5", "--", "2", "3", "4"]
     );
+    test!(
+        test_double_dash_overlap,
+        ["add", "3-5", "4", "--", "2-4", "3", "^2"],
+        ["add", "3", "4", "5", "--", "3", "4"]
+    );
+
+    test!(test_exclude, ["add", "1-5", "^4"], ["add", "1", "2", "3", "5"]);
+    test!(test_exclude_range, ["add", "1-6", "!2-4"], ["add", "1", "5", "6"]);
+    test!(test_exclude_first, ["add", "^2", "1-3"], ["add", "1", "3"]);
+    test!(test_exclude_only, ["add", "^2"], ["add"]);
 
     test!(test_zeros_1, ["add", "0"], ["add", "0"]);
     test!(test_zeros_2, ["add", "0-1"], ["add", "0", "1"]);
     test!(test_zeros_3, ["add", "0-0"], ["add", "0"]);
 
+    test!(test_list, ["add", "5,1,3-4"], ["add", "5", "1", "3", "4"]);
+    test!(test_list_exclude, ["add", "1-5", "^2,4"], ["add", "1", "3", "5"]);
+    test!(test_list_invalid, ["add", "1,x"], ["add", "1,x"]);
+    test!(test_list_trailing, ["add", "1,"], ["add", "1,"]);
+
+    // Categories are left alone when nothing falls under them
+    test!(
+        test_category_empty,
+        ["add", "untracked", "M"],
+        ["add", "untracked", "M"]
+    );
+
+    // Backslashes escape anything that would be expanded
+    test!(
+        test_escape,
+        ["add", "\\2", "\\2-3", "\\x"],
+        ["add", "2", "2-3", "\\x"]
+    );
+
+    // Open-ended forms are left alone when they select nothing
+    test!(test_open_ended_empty, ["add", "3-", "all"], ["add", "3-", "all"]);
+    test!(test_star, ["add", "*"], ["add", "*"]);
+    test!(test_from_end_empty, ["add", "~1"], ["add", "~1"]);
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range("4", 6), Some((4, 4)));
+        assert_eq!(parse_range("6-2", 6), Some((2, 6)));
+        assert_eq!(parse_range("4-", 6), Some((4, 6)));
+        assert_eq!(parse_range("all", 6), Some((1, 6)));
+        assert_eq!(parse_range("*", 6), None);
+        assert_eq!(parse_range("all", 0), None);
+        assert_eq!(parse_range("7-", 6), None);
+        assert_eq!(parse_range("~1", 6), Some((6, 6)));
+        assert_eq!(parse_range("~3-", 6), Some((4, 6)));
+        assert_eq!(parse_range("2-~2", 6), Some((2, 5)));
+        assert_eq!(parse_range("~7", 6), None);
+        assert_eq!(parse_range("~0", 6), None);
+        assert_eq!(parse_range("-4", 6), None);
+    }
+
+    // Git's own options come before the command, and can take values
+    test!(
+        test_globals,
+        ["-C", "add", "-c", "x=y", "add", "1"],
+        ["-C", "add", "-c", "x=y", "add", "1"]
+    );
+    test!(test_not_a_command, ["foo", "add", "1"], ["foo", "add", "1"]);
+
+    #[test]
+    fn test_split_globals() {
+        let args = string_vec(["git", "-C", "a", "-c", "x=y", "-C", "b", "st"]);
+        assert_eq!(globals(&args), (vec!["a", "b"], vec!["-c", "x=y"]));
+    }
+
     // Filenames containing dashed dates
     test!(test_date_filename, ["add", "2021-01-31"], ["add", "2021-01-31"]);
 }
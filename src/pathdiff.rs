@@ -21,8 +21,8 @@ where
                 }
                 (None, _) => cs.push(Component::ParentDir),
                 (Some(a), Some(b)) if cs.is_empty() && a == b => (),
-                (Some(a), Some(b)) if b == Component::CurDir => cs.push(a),
-                (Some(_), Some(b)) if b == Component::ParentDir => return None,
+                (Some(a), Some(Component::CurDir)) => cs.push(a),
+                (Some(_), Some(Component::ParentDir)) => return None,
                 (Some(a), Some(_)) => {
                     cs.push(Component::ParentDir);
                     for _ in itb {
@@ -37,3 +37,20 @@ where
         Some(cs.iter().map(|c| c.as_os_str()).collect())
     }
 }
+
+/// Lexically resolves the `.` and `..` components of `path`, without
+/// reading the file system.
+pub fn normalize<P: AsRef<Path>>(path: P) -> PathBuf {
+    let mut cs = vec![];
+    for c in path.as_ref().components() {
+        match (c, cs.last()) {
+            (Component::CurDir, _) => {}
+            (Component::ParentDir, Some(Component::Normal(_))) => {
+                cs.pop();
+            }
+            (Component::ParentDir, Some(Component::RootDir)) => {}
+            _ => cs.push(c),
+        }
+    }
+    cs.iter().map(|c| c.as_os_str()).collect()
+}
@@ -0,0 +1,137 @@
+use crate::cache::{Entry, Kind};
+use crate::git::Dirs;
+use crate::indexer::Indexer;
+use crate::log;
+use crate::prelude::*;
+use crate::status::{uncolor, unquote};
+
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Header fields of a commit in `git log` and `git show`.
+const HEADERS: [&str; 6] =
+    ["Merge", "Author", "AuthorDate", "Commit", "CommitDate", "Date"];
+
+/// Checks if this line of `git log` describes a commit, rather than
+/// listing a file.
+fn is_commit_line(git_log: &GitLog, line: &str) -> bool {
+    match git_log {
+        GitLog::Oneline => log::hash(git_log, line).is_some(),
+        GitLog::Normal => {
+            line.starts_with("commit ")
+                // commit messages are indented by 4 spaces
+                || line.starts_with("    ")
+                || line.split_once(':').is_some_and(|v| HEADERS.contains(&v.0))
+        }
+        GitLog::Custom => true,
+    }
+}
+
+/// Resolves a rename to its new name.
+///
+/// Example:
+/// ```text
+/// old.rs => new.rs          -> new.rs
+/// src/{old => new}/main.rs  -> src/new/main.rs
+/// src/{ => new}/main.rs     -> src/new/main.rs
+/// ```
+fn renamed(path: &str) -> String {
+    let Some((old, new)) = path.split_once(" => ") else {
+        return path.to_string();
+    };
+    match (old.rsplit_once('{'), new.split_once('}')) {
+        (Some((prefix, _)), Some((new, suffix))) => {
+            format!("{prefix}{new}{suffix}").replace("//", "/")
+        }
+        _ => new.to_string(),
+    }
+}
+
+/// Gets the file name out of a line of `--stat` output, which looks
+/// like ` src/main.rs | 10 +++++-----`. Names that git shortened to fit
+/// the screen (`.../main.rs`) can't be used, and are skipped.
+fn stat(line: &str) -> Option<String> {
+    let (path, _) = line.split_once(" | ")?;
+    let path = unquote(path.trim());
+    (!path.starts_with("...")).then(|| renamed(&path))
+}
+
+/// Numbers the files listed by `git diff --name-only`, `git ls-files`,
+/// `git log --stat` and the like.
+pub(crate) struct Paths {
+    git_log: Option<GitLog>,
+    names: Names,
+    /// The directory that listed paths are relative to.
+    dir: PathBuf,
+    seen: HashSet<String>,
+}
+
+impl Paths {
+    pub fn new(git_cmd: &GitCommand, dirs: &Dirs) -> Self {
+        let (git_log, git_names) = match git_cmd {
+            GitCommand::Log(v, n) | GitCommand::Show(v, n) => (Some(v), n),
+            GitCommand::Diff(n) | GitCommand::LsFiles(n) => (None, n),
+            _ => (None, &GitNames::default()),
+        };
+        let dir = match git_names.relative {
+            true => &dirs.base,
+            false => &dirs.toplevel,
+        };
+        Self {
+            git_log: git_log.cloned(),
+            names: git_names.names,
+            dir: dir.to_path_buf(),
+            seen: HashSet::new(),
+        }
+    }
+}
+
+impl Indexer for Paths {
+    fn kind(&self) -> Kind {
+        Kind::Files
+    }
+
+    /// Cached paths are relative to the directory on the first line.
+    fn header(&self) -> Option<&Path> {
+        Some(&self.dir)
+    }
+
+    fn index(&mut self, line: &str) -> Option<String> {
+        let line = String::from_utf8_lossy(&uncolor(line)).to_string();
+        if line.is_empty() {
+            return None;
+        }
+        if let Some(git_log) = &self.git_log {
+            if is_commit_line(git_log, &line) {
+                return None;
+            }
+        }
+        let path = match self.names {
+            Names::None => return None,
+            Names::Plain => unquote(line.rsplit('\t').next()?),
+            Names::Tabbed => renamed(&unquote(line.rsplit_once('\t')?.1)),
+            Names::Stat => stat(&line)?,
+        };
+        // files listed by more than one commit are numbered once
+        if !self.seen.insert(path.clone()) {
+            return None;
+        }
+        Some(Entry { path, ..Default::default() }.to_string())
+    }
+
+    /// Lines other than paths, such as commits or diffs, are printed
+    /// just as git prints them.
+    fn print(
+        &self,
+        out: &mut dyn Write,
+        line: &[u8],
+        number: Option<usize>,
+    ) -> io::Result<()> {
+        if let Some(n) = number {
+            write!(out, "{: <3}", n)?;
+        }
+        out.write_all(line)?;
+        writeln!(out)
+    }
+}
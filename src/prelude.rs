@@ -3,6 +3,7 @@ use std::ffi::OsStr;
 use std::process::{Command, ExitCode, ExitStatus};
 
 pub(crate) use crate::cache::Cache;
+pub(crate) use crate::config::Config;
 pub(crate) use crate::error::*;
 pub(crate) use crate::git_cmd::*;
 pub(crate) use crate::pathdiff;
@@ -0,0 +1,50 @@
+use crate::cache::Kind;
+use crate::indexer::Indexer;
+use crate::status::uncolor;
+
+/// Gets the branch named by this line of `git branch`, if any.
+///
+/// Example:
+/// ```text
+/// * main
+///   feature
+/// + checked-out-in-another-worktree
+///   remotes/origin/HEAD -> origin/main     (skipped)
+///   remotes/origin/main                    (stored as origin/main)
+/// * (HEAD detached at 3e1b5c0)             (skipped)
+/// ```
+fn branch(line: &str) -> Option<String> {
+    let line = line.get(2..)?;
+    let mut words = line.split_whitespace();
+    let name = words.next().filter(|v| !v.starts_with('('))?;
+    if words.next() == Some("->") {
+        return None;
+    }
+    Some(name.strip_prefix("remotes/").unwrap_or(name).to_string())
+}
+
+/// Gets the tag named by this line of `git tag`, which may be followed
+/// by its annotation with `-n`.
+fn tag(line: &str) -> Option<String> {
+    line.split_whitespace().next().map(|v| v.to_string())
+}
+
+/// Numbers the refs listed by `git branch` or `git tag`.
+pub(crate) enum Refs {
+    Branches,
+    Tags,
+}
+
+impl Indexer for Refs {
+    fn kind(&self) -> Kind {
+        Kind::Refs
+    }
+
+    fn index(&mut self, line: &str) -> Option<String> {
+        let line = String::from_utf8_lossy(&uncolor(line)).to_string();
+        match self {
+            Self::Branches => branch(&line),
+            Self::Tags => tag(&line),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::cache::Kind;
+use crate::error;
+use crate::git::{self, Dirs};
+use crate::indexer::Indexer;
+use crate::prelude::*;
+use crate::status::uncolor;
+
+use std::fs;
+use std::path::Path;
+use std::process::{Command, ExitStatus};
+
+/// Gets `N` out of `stash@{N}`.
+fn stash_index(arg: &str) -> Option<usize> {
+    arg.strip_prefix("stash@{")?.strip_suffix('}')?.parse().ok()
+}
+
+/// Numbers the stashes listed by `git stash list`.
+pub(crate) struct Stashes(std::vec::IntoIter<String>);
+
+impl Stashes {
+    pub fn new(git_dir: &Path) -> Self {
+        Self(git::stashes(git_dir).into_iter())
+    }
+}
+
+impl Indexer for Stashes {
+    fn kind(&self) -> Kind {
+        Kind::Stashes
+    }
+
+    fn index(&mut self, line: &str) -> Option<String> {
+        match uncolor(line).starts_with(b"stash@{") {
+            true => self.0.next(),
+            false => None,
+        }
+    }
+}
+
+/// `git stash drop` takes one stash at a time, so each one is dropped
+/// in its own run, from the oldest to the newest. This way, dropping a
+/// stash never shifts the numbers of those yet to be dropped.
+fn drop_each(mut argh: Command) -> Result<ExitStatus> {
+    let args: Vec<String> =
+        argh.get_args().map(|v| v.to_string_lossy().to_string()).collect();
+    let mut indices: Vec<usize> =
+        args.iter().filter_map(|v| stash_index(v)).collect();
+    if indices.len() < 2 {
+        return Ok(argh.status()?);
+    }
+    indices.sort_unstable_by(|a, b| b.cmp(a));
+    let others = args.iter().filter(|v| stash_index(v).is_none());
+    let others: Vec<&String> = others.collect();
+
+    let mut status = None;
+    for i in indices {
+        let mut git = Command::new(argh.get_program());
+        if let Some(dir) = argh.get_current_dir() {
+            git.current_dir(dir);
+        }
+        git.args(&others).arg(format!("stash@{{{i}}}"));
+        match git.status()? {
+            v if v.success() => status = Some(v),
+            v => return Ok(v),
+        }
+    }
+    Ok(status.unwrap_or_default())
+}
+
+pub fn git_stash(
+    mut argh: Command,
+    dirs: &Dirs,
+    git_stash: GitStash,
+) -> Result<ExitStatus> {
+    if !git_stash.is_numbered() {
+        return Ok(argh.status()?);
+    }
+
+    // Stash numbers shift whenever the stash list changes, so the
+    // cached numbers are only safe to use if it hasn't.
+    let path = Kind::Stashes.path(&dirs.git_dir, &dirs.base);
+    let cached = fs::read_to_string(path)?;
+    let stashes = git::stashes(&dirs.git_dir);
+    let listed = stashes.iter().take(cached.lines().count());
+    if !listed.eq(cached.lines()) {
+        eprintln!(
+            "fatal: the stash list changed since it was numbered. \
+            Run `git nu stash list` to number it again."
+        );
+        return error!(StaleCache);
+    }
+
+    match git_stash.is("drop") {
+        true => drop_each(argh),
+        false => Ok(argh.status()?),
+    }
+}
@@ -1,9 +1,14 @@
+use crate::cache::{Entry, Kind, Section};
+use crate::git::Dirs;
+use crate::indexer::Indexer;
+use crate::pathdiff;
 use crate::prelude::*;
 
-use std::fs::File;
-use std::io::{BufRead, BufReader, LineWriter, Write};
-use std::path::PathBuf;
-use std::process::{Command, ExitStatus, Stdio};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
 /// Removes all ANSI color codes
 pub fn uncolor(src: &str) -> Vec<u8> {
@@ -20,115 +25,325 @@ pub fn uncolor(src: &str) -> Vec<u8> {
     b
 }
 
-struct State {
-    seen_untracked: bool,
-    count: usize,
+/// Decodes a path that git quoted for having unusual characters in it
+/// (see `core.quotePath`), such as `"caf\303\251 menu.txt"`. Paths that
+/// aren't quoted are returned as they are.
+pub fn unquote(path: &str) -> String {
+    let quoted = path.strip_prefix('"').and_then(|v| v.strip_suffix('"'));
+    let Some(quoted) = quoted else { return path.to_string() };
+    let (mut decoded, mut bytes) = (vec![], quoted.bytes());
+    while let Some(c) = bytes.next() {
+        if c != b'\\' {
+            decoded.push(c);
+            continue;
+        }
+        let c = match bytes.next() {
+            Some(b'a') => b'\x07',
+            Some(b'b') => b'\x08',
+            Some(b't') => b'\t',
+            Some(b'n') => b'\n',
+            Some(b'v') => b'\x0b',
+            Some(b'f') => b'\x0c',
+            Some(b'r') => b'\r',
+            // a byte as three octal digits, such as `\303`
+            Some(c @ b'0'..=b'3') => bytes
+                .by_ref()
+                .take(2)
+                .fold(c - b'0', |n, c| n << 3 | c.wrapping_sub(b'0') & 7),
+            // `\"` and `\\`
+            Some(c) => c,
+            None => break,
+        };
+        decoded.push(c);
+    }
+    String::from_utf8_lossy(&decoded).to_string()
 }
 
-/// Uses the `\t` character to differentiate between lines that
-/// contain pathspecs and those that do not.
+/// Reads the entries of `git status --porcelain=v2 -z`, in the order
+/// that git lists them. Paths are relative to the workspace root.
 ///
-/// if None is returned, the line will not be added to cache.
-fn normal(state: &mut State, line: String) -> Option<String> {
-    if state.count > MAX_CACHE_SIZE {
-        println!("{}", line);
-        return None;
-    }
-    state.seen_untracked |= line.starts_with("Untracked files:");
-    if !line.starts_with('\t') {
-        println!("{}", line);
-        return None;
-    }
-
-    println!("{}{}", state.count, line);
-    state.count += 1;
-
-    let line = &uncolor(&line);
-    let line: &str = std::str::from_utf8(line).unwrap();
-    let line = line
-        .rsplit_once('\t')
-        .expect("There should be a tab character in the line")
-        .1;
-
-    // Example:
-    // ```
-    // Changes not staged for commit:
-    // 1       modified:   core/status.rs
-    //
-    // Untracked files:
-    // 2       core/line.rs
-    // ```
-    let (delta, pathspec) = match state.seen_untracked {
-        false => line
-            .split_once(':')
-            .expect("There should be a `:` character in the line"),
-        true => ("", line),
-    };
-
-    let pathspec = pathspec.trim_start();
-
-    let pathspec = match delta {
-        // Example:
-        // ```
-        // Changes to be committed:
-        // 1       renamed:    README.md -> BUILD.md
-        // ```
-        "renamed" => pathspec
-            .split_once("->")
-            .expect("There should be a `->` in the line with a rename")
-            .1
-            .trim_start(),
-        _ => pathspec,
-    };
-
-    Some(pathspec.to_string())
+/// Each record ends with a NUL, and renames and copies are followed by
+/// one more record with the original path:
+/// ```text
+/// 1 <XY> <sub> <mH> <mI> <mW> <hH> <hI> <path>
+/// 2 <XY> <sub> <mH> <mI> <mW> <hH> <hI> <X><score> <path>\0<orig>
+/// u <XY> <sub> <m1> <m2> <m3> <mW> <h1> <h2> <h3> <path>
+/// ? <path>
+/// ! <path>
+/// ```
+fn porcelain(stdout: &[u8]) -> Vec<Entry> {
+    let mut records = stdout.split(|b| *b == 0).map(String::from_utf8_lossy);
+    let mut entries = vec![];
+    while let Some(record) = records.next() {
+        let (xy, fields) = match record.get(..2) {
+            Some("1 ") => (record.get(2..4), 9),
+            Some("2 ") => (record.get(2..4), 10),
+            Some("u ") => (record.get(2..4), 11),
+            Some("? ") => (Some("??"), 2),
+            Some("! ") => (Some("!!"), 2),
+            _ => continue,
+        };
+        let orig = match record.starts_with('2') {
+            true => records.next().map(|v| v.to_string()),
+            false => None,
+        };
+        let path = record.splitn(fields, ' ').nth(fields - 1);
+        if let (Some(xy), Some(path)) = (xy, path) {
+            let entry = Entry::new(Section::from_xy(xy), xy, path);
+            entries.push(Entry { orig, ..entry });
+        }
+    }
+    entries
+}
+
+/// Runs `argh` again in porcelain v2 format, which lists the same
+/// entries as `argh` would, but exactly. Unlike the formats meant for
+/// people, this one is never translated.
+fn entries(argh: &Command) -> Vec<Entry> {
+    let mut cmd = Command::new(argh.get_program());
+    if let Some(dir) = argh.get_current_dir() {
+        cmd.current_dir(dir);
+    }
+    let mut args: Vec<_> = argh.get_args().collect();
+    // the format options have to come after the user's, but before `--`
+    let at = args.iter().position(|v| *v == "--").unwrap_or(args.len());
+    args.splice(at..at, ["--porcelain=v2", "-z"].map(OsStr::new));
+    match cmd.args(args).stderr(Stdio::null()).output() {
+        Ok(output) => porcelain(&output.stdout),
+        Err(_) => vec![],
+    }
 }
 
-fn short(state: &mut State, line: String) -> String {
-    println!("{: <3}{}", state.count, line);
-    state.count += 1;
-    String::from_utf8_lossy(&uncolor(&line))[3..].to_string()
+/// Makes `path`, relative to the workspace root, relative to the
+/// current directory instead. Directories keep their trailing `/`.
+fn relative(path: &str, dirs: &Dirs) -> String {
+    let diff = pathdiff::diff_paths(dirs.toplevel.join(path), &dirs.base);
+    let diff = diff.unwrap_or_else(|| PathBuf::from(path));
+    let diff = diff.to_string_lossy();
+    match (diff.as_ref(), path.ends_with('/')) {
+        ("", _) => "./".to_string(),
+        (v, true) => format!("{v}/"),
+        (v, false) => v.to_string(),
+    }
+}
+
+/// Quotes `path` in C style the way `git status` shows it, if it has
+/// unusual characters in it: control characters, `"` and `\` always,
+/// non-ASCII ones with `core.quotePath`, and spaces in the short format.
+fn quote(path: &str, quote_path: bool, quote_space: bool) -> String {
+    let mut quoted = String::new();
+    let mut must_quote = quote_space && path.contains(' ');
+    for c in path.chars() {
+        let escape = match c {
+            '\x07' => "\\a",
+            '\x08' => "\\b",
+            '\t' => "\\t",
+            '\n' => "\\n",
+            '\x0b' => "\\v",
+            '\x0c' => "\\f",
+            '\r' => "\\r",
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            _ if c.is_ascii_control() || (!c.is_ascii() && quote_path) => {
+                let mut buf = [0; 4];
+                for b in c.encode_utf8(&mut buf).bytes() {
+                    quoted += &format!("\\{b:03o}");
+                }
+                must_quote = true;
+                continue;
+            }
+            _ => {
+                quoted.push(c);
+                continue;
+            }
+        };
+        quoted += escape;
+        must_quote = true;
+    }
+    match must_quote {
+        true => format!("\"{quoted}\""),
+        false => quoted,
+    }
+}
+
+/// The entries listed by `git status`'s normal output, grouped by
+/// the sections they are listed in, in the order that git lists them:
+/// staged, unmerged, unstaged, untracked and ignored. Files with both
+/// staged and unstaged changes are listed in each of those sections.
+fn sections(entries: Vec<Entry>) -> Vec<Vec<Entry>> {
+    use Section::*;
+    let mut sections: [Vec<Entry>; 5] = Default::default();
+    for entry in entries {
+        let (x, y) = entry.xy.split_at(1);
+        // only the side that was renamed or copied has an original path
+        let side = |section, xy: String| {
+            let orig = entry.orig.clone().filter(|_| xy.contains(['R', 'C']));
+            Entry { orig, ..Entry::new(section, &xy, &entry.path) }
+        };
+        match entry.section {
+            Some(Staged | Unstaged) => {
+                if x != "." {
+                    sections[0].push(side(Staged, format!("{x}.")));
+                }
+                if y != "." {
+                    sections[2].push(side(Unstaged, format!(".{y}")));
+                }
+            }
+            Some(Unmerged) => sections[1].push(entry),
+            Some(Untracked) => sections[3].push(entry),
+            _ => sections[4].push(entry),
+        }
+    }
+    sections.into()
 }
 
-pub fn git_status(
-    mut argh: Command,
-    git_dir: &PathBuf,
-    git_cmd: GitCommand,
-) -> Result<ExitStatus> {
-    let mut git = argh.stdout(Stdio::piped()).spawn()?;
+/// Numbers the files listed by `git status`.
+///
+/// The files are read from a run of `git status --porcelain=v2 -z`
+/// beforehand, so the cache holds the exact paths no matter how they
+/// are shown. Git's own output is still what gets printed, and each
+/// line is matched with a file by the path that it ends with, so that
+/// neither git's order nor its translations matter.
+pub(crate) struct Status {
+    format: StatusFormat,
+    /// Files of each section, by how git shows their paths. The short
+    /// format lists every file in one section.
+    sections: Vec<HashMap<String, Entry>>,
+    /// The section of the list of files being read, which is `None`
+    /// outside of a list, and `Some(None)` in a list of anything else.
+    section: Option<Option<usize>>,
+    /// Sections before this one have already been listed.
+    next: usize,
+    cwd: PathBuf,
+}
 
-    let lines = match git.stdout.take() {
-        Some(v) => BufReader::new(v).lines().filter_map(|v| v.ok()),
-        None => return Ok(git.wait()?),
-    };
+impl Status {
+    pub fn new(git_status: &GitStatus, argh: &Command, dirs: &Dirs) -> Self {
+        let config = &git_status.config;
+        let format = git_status.format;
+        let short = format == StatusFormat::Short;
+        // paths are shown relative to the current directory, unless
+        // `status.relativePaths` is off, but always cached that way
+        let shown = |path: &str| {
+            let path = match config.relative_paths {
+                true => relative(path, dirs),
+                false => path.to_string(),
+            };
+            quote(&path, config.quote_path, short)
+        };
+        let section = |entries: Vec<Entry>| {
+            let entries = entries.into_iter().map(|entry| {
+                let path = shown(&entry.path);
+                let shown = match &entry.orig {
+                    Some(orig) => format!("{} -> {path}", shown(orig)),
+                    None => path,
+                };
+                let orig = entry.orig.as_deref().map(|v| relative(v, dirs));
+                let path = relative(&entry.path, dirs);
+                (shown, Entry { path, orig, ..entry })
+            });
+            entries.collect()
+        };
+        let entries = entries(argh);
+        let sections = match format {
+            StatusFormat::Short => vec![section(entries)],
+            _ => sections(entries).into_iter().map(section).collect(),
+        };
+        Self {
+            format,
+            sections,
+            section: None,
+            next: 0,
+            cwd: dirs.base.clone(),
+        }
+    }
 
-    let cwd = argh.get_current_dir().unwrap();
-    let mut cache_filepath = cwd.join(&git_dir);
-    cache_filepath.push(CACHE_FILE_NAME);
+    /// Finds the file listed by `line`, a line of the normal format.
+    /// Each list of files is matched with the next section that has
+    /// the first of them, and lists of anything else are left alone.
+    fn read(&mut self, line: &str) -> Option<Entry> {
+        let Some(line) = line.strip_prefix('\t') else {
+            self.section = None;
+            return None;
+        };
+        // the path follows a label, such as `modified:`, padded with
+        // spaces, and the longest path that matches wins
+        let paths = std::iter::once(line).chain(
+            line.match_indices(' ').filter_map(|(i, _)| line.get(i + 1..)),
+        );
+        let paths: Vec<_> = paths.collect();
+        let find = |section: &HashMap<String, Entry>| {
+            paths.iter().copied().find(|v| section.contains_key(*v))
+        };
+        if self.section.is_none() {
+            let mut sections = self.sections.iter().enumerate();
+            let found =
+                sections.find(|(i, v)| *i >= self.next && find(v).is_some());
+            let i = found.map(|(i, _)| i);
+            self.section = Some(i);
+            self.next = i.map_or(self.next, |i| i + 1);
+        }
+        let section = &mut self.sections[self.section??];
+        let path = find(section)?.to_string();
+        section.remove(&path)
+    }
+}
 
-    let writer = &mut LineWriter::new(File::create(&cache_filepath)?);
+impl Indexer for Status {
+    fn kind(&self) -> Kind {
+        Kind::Files
+    }
 
-    // first line of the cache file is the current directory
-    writeln!(writer, "{}", cwd.display()).unwrap();
+    /// First line of the cache file is the current directory.
+    fn header(&self) -> Option<&Path> {
+        Some(&self.cwd)
+    }
 
-    let state = &mut State { seen_untracked: false, count: 1 };
+    /// An empty status means that there is nothing left to number.
+    fn is_complete(&self) -> bool {
+        true
+    }
 
-    use GitCommand::Status;
+    /// In the short format, every line is a file, except for the
+    /// branch header of `--branch` (such as `## main...origin/main
+    /// [ahead 1]`).
+    fn index(&mut self, line: &str) -> Option<String> {
+        let line = String::from_utf8_lossy(&uncolor(line)).to_string();
+        let entry = match self.format {
+            StatusFormat::Short if line.starts_with("## ") => None,
+            StatusFormat::Short => self.sections[0].remove(line.get(3..)?),
+            _ => self.read(&line),
+        };
+        Some(entry?.to_string())
+    }
 
-    if let Status(GitStatus::Short) = git_cmd {
-        for line in lines {
-            writeln!(writer, "{}", short(state, line)).unwrap();
-        }
-    } else if let Status(GitStatus::Normal) = git_cmd {
-        for line in lines {
-            if let Some(v) = normal(state, line) {
-                writeln!(writer, "{v}").unwrap();
-            }
+    fn print(
+        &self,
+        out: &mut dyn Write,
+        line: &[u8],
+        number: Option<usize>,
+    ) -> io::Result<()> {
+        match (self.format, number) {
+            (StatusFormat::Short, Some(n)) => write!(out, "{: <3}", n)?,
+            (_, Some(n)) => write!(out, "{}", n)?,
+            (_, None) => {}
         }
+        out.write_all(line)?;
+        writeln!(out)
     }
+}
 
-    // close the writer
-    writer.flush().ok();
+#[cfg(test)]
+mod tests {
+    use super::unquote;
 
-    Ok(git.wait()?)
+    #[test]
+    fn test_unquote() {
+        assert_eq!(unquote("src/main.rs"), "src/main.rs");
+        assert_eq!(unquote("\"caf\\303\\251 menu.txt\""), "café menu.txt");
+        assert_eq!(unquote("\"q\\\"uote\""), "q\"uote");
+        assert_eq!(unquote("\"back\\\\slash\""), "back\\slash");
+        assert_eq!(unquote("\"tab\\there\""), "tab\there");
+        assert_eq!(unquote("\"unclosed"), "\"unclosed");
+    }
 }
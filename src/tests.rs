@@ -30,7 +30,7 @@ macro_rules! color {
         }
     };
 }
-color!(green, 32, yellow, 33, purple, 35, cyan, 36, gray, 37);
+color!(purple, 35, cyan, 36, gray, 37);
 
 struct Test {
     dir: PathBuf,
@@ -59,9 +59,9 @@ impl Test {
         let mut x = vec!["git".to_string()];
         x.extend(args.into_iter().map(|v| v.as_ref().to_string()));
         let cwd = self.dir.join(rel_dir);
-        let (cwd, git_dir, git_aliases) = prefetch(cwd)?;
-        let cache = Cache::new(&git_dir, &cwd);
-        Ok(parse::parse(&x, git_aliases, cache, vec![]).0)
+        let (dirs, config) = prefetch(cwd, &x)?;
+        let cache = Cache::new(&dirs.git_dir, &dirs.base);
+        Ok(parse::parse(&x, &config, cache, vec![]).0)
     }
 
     /// Run a shell command at a directory relative to the test root dir.
@@ -135,7 +135,7 @@ fn env_var(name: &str) -> String {
 /// 1. Clear and re-create the test directory
 /// 2. Set the $PATH to ensure that the debug binary is front-and-center.
 fn prep_test(name: &str) -> PathBuf {
-    let test_dir = env::temp_dir().join(TEST_DIR).join(&name);
+    let test_dir = env::temp_dir().join(TEST_DIR).join(name);
     test_dir.exists().then(|| fs::remove_dir_all(&test_dir));
     fs::create_dir_all(&test_dir).unwrap();
 
@@ -188,9 +188,7 @@ test!(
     ["add", "B", "C", "D", "F"]
 );
 
-// This just tests that the cache can be read more than once.
-// Possible idea: make the cache readable only once.
-// (If a number is called again, just insert nothing.)
+// Overlapping ranges expand each cached file only once.
 test!(
     range_overlap,
     |t| {
@@ -199,7 +197,166 @@ test!(
         let _ = t.gitnu("", ["status"]);
     },
     ["add", "2-4", "3-5"],
-    ["add", "B", "C", "D", "C", "D", "E"]
+    ["add", "B", "C", "D", "E"]
+);
+
+// Everything but the excluded files.
+test!(
+    range_exclusion,
+    |t| {
+        t.sh("", "git init -b main");
+        t.sh("", "touch A B C D E F");
+        let _ = t.gitnu("", ["status"]);
+    },
+    ["add", "1-6", "^2", "!4-5"],
+    ["add", "A", "C", "F"]
+);
+
+// Open-ended ranges and `all` stop at the last cached file.
+test!(
+    range_open_ended,
+    |t| {
+        t.sh("", "git init -b main");
+        t.sh("", "touch A B C D E");
+        let _ = t.gitnu("", ["status"]);
+    },
+    ["add", "4-", "all"],
+    ["add", "D", "E", "A", "B", "C"]
+);
+
+// Indices prefixed with `~` count from the end of the cache.
+test!(
+    range_from_end,
+    |t| {
+        t.sh("", "git init -b main");
+        t.sh("", "touch A B C D E");
+        let _ = t.gitnu("", ["status"]);
+    },
+    ["add", "~1", "~4-~3"],
+    ["add", "E", "B", "C"]
+);
+
+// Comma-separated lists expand in order, unless the list is actually
+// the name of a file.
+test!(
+    comma_list,
+    |t| {
+        t.sh("", "git init -b main");
+        t.sh("", "touch A B C D E 1,2");
+        let _ = t.gitnu("", ["status"]);
+    },
+    ["add", "6,2-3", "1,2"],
+    ["add", "E", "A", "B", "1,2"]
+);
+
+// Status categories expand to every cached file listed under them.
+macro_rules! category_test {
+    ($name:ident, $status:expr, $input_args:expr, $output_args:expr) => {
+        test!(
+            $name,
+            |t| {
+                t.sh("", "git init -b main");
+                t.sh("", "touch A B C D E");
+                t.sh("", "git add A B C && git commit -m x");
+                t.sh("", "rm C && echo x > A && echo x > B && git add B D");
+                let _ = t.gitnu("", $status);
+            },
+            $input_args,
+            $output_args
+        );
+    };
+}
+category_test!(
+    category_untracked,
+    ["status"],
+    ["add", "untracked"],
+    ["add", "E"]
+);
+category_test!(
+    category_staged,
+    ["status"],
+    ["add", "staged"],
+    ["add", "B", "D"]
+);
+category_test!(
+    category_unstaged,
+    ["status"],
+    ["add", "unstaged"],
+    ["add", "A", "C"]
+);
+category_test!(
+    category_sigils,
+    ["status", "-s"],
+    ["add", "M", "?"],
+    ["add", "A", "B", "E"]
+);
+category_test!(
+    category_exclude,
+    ["status", "--short"],
+    ["add", "all", "^deleted,added"],
+    ["add", "A", "B", "E"]
+);
+category_test!(
+    category_short_forms,
+    ["status", "-s"],
+    ["add", "A,D"],
+    ["add", "D", "C"]
+);
+
+// Where a ref could go, categories are only expanded after `--`.
+category_test!(
+    category_ref_position,
+    ["status"],
+    ["checkout", "deleted", "--", "deleted"],
+    ["checkout", "deleted", "--", "C"]
+);
+
+// Globs prefixed with `@` only match what the last status listed.
+test!(
+    glob_cached_files,
+    |t| {
+        t.sh("", "git init -b main");
+        t.sh("", "mkdir src && touch a.rs b.rs c.md src/d.rs src/e.md");
+        let _ = t.gitnu("", ["status", "-uall"]);
+        t.sh("", "touch f.rs");
+    },
+    ["add", "@*.rs", "@src/**", "^@**.md"],
+    ["add", "a.rs", "b.rs", "src/d.rs"]
+);
+
+// Globs match paths as seen from the current directory.
+test!(glob_relative_to_cwd, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "mkdir src && touch a.rs src/b.rs");
+    let _ = t.gitnu("", ["status", "-uall"]);
+    let ls = t.sh("src", "git nu ls '@*.rs'").stdout;
+    assert_eq!(ls, "2  ?? ../src/b.rs\n");
+    assert_eq!(t.sh("src", "git nu ls '@../*'").stdout, "1  ?? ../a.rs\n");
+});
+
+// Numbers that are also file names refer to the file by default.
+test!(
+    collision_path,
+    |t| {
+        t.sh("", "git init -b main");
+        t.sh("", "touch 2 A B C");
+        let _ = t.gitnu("", ["status"]);
+    },
+    ["add", "2", "3-4", "\\4"],
+    ["add", "2", "B", "C", "4"]
+);
+
+// ...unless the repo prefers the cache.
+test!(
+    collision_warn,
+    |t| {
+        t.sh("", "git init -b main");
+        t.sh("", "git config gitnu.collision warn");
+        t.sh("", "touch 2 A B C");
+        let _ = t.gitnu("", ["status"]);
+    },
+    ["add", "2", "\\2"],
+    ["add", "A", "2"]
 );
 
 // Unindexed numbers will appear as the number itself, since it does
@@ -258,19 +415,80 @@ test!(dont_create_cache_file_without_repo, |t| {
     assert_eq!(t.sh("", "ls -lA").stdout.trim(), "total 0");
 });
 
+// Outside of a repository, `exec`, `which` and `completions` run with
+// nothing to expand, and `ls` says why it can't.
+test!(natives_without_repo, |t| {
+    assert_eq!(t.sh("", "git nu exec echo 1 all").stdout, "1 all\n");
+    assert_eq!(t.sh("", "git nu which A").stdout, "A\n");
+    let output = t.sh("", "git nu which 1 2>&1");
+    assert_eq!(output.stdout, "error: `1` is not in the cache\n");
+    assert_eq!(output.exit_code, Some(1));
+    assert!(t.sh("", "git nu completions bash").stdout.contains("git"));
+    let output = t.sh("", "git nu ls 2>&1");
+    assert_eq!(output.stdout, "fatal: not a git repository\n");
+    assert_eq!(output.exit_code, Some(1));
+    let result = t.gitnu("", ["ls"]);
+    assert_eq!(result.err(), Some(Error::NotGitRepository));
+    assert_eq!(t.sh("", "ls -A").stdout, "");
+});
+
 // Determined in ../git_cmd.rs
-// where it's specified if a command should be skipped because it might
-// be part of a flag value
-test!(
+// where it's specified which options of each command take a value, so
+// that the value isn't mistaken for a file number.
+macro_rules! skip_flags {
+    ($name:ident, $input_args:expr, $output_args:expr) => {
+        test!(
+            $name,
+            |t| {
+                t.sh("", "git init -b main");
+                t.sh("", "touch A B C");
+                let _ = t.gitnu("", ["status"]);
+            },
+            $input_args,
+            $output_args
+        );
+    };
+}
+skip_flags!(
     skip_flags,
-    |t| {
-        t.sh("", "git init -b main");
-        t.sh("", "touch A B C");
-        let _ = t.gitnu("", ["status"]);
-    },
     ["log", "-n", "2", "--oneline", "3"],
     ["log", "-n", "2", "--oneline", "C"]
 );
+skip_flags!(
+    skip_flags_long,
+    ["log", "--max-count", "2", "--skip=1", "3"],
+    ["log", "--max-count", "2", "--skip=1", "C"]
+);
+skip_flags!(
+    skip_flags_grouped,
+    ["commit", "-am", "2", "3"],
+    ["commit", "-am", "2", "C"]
+);
+skip_flags!(
+    skip_flags_attached,
+    ["commit", "-m2", "-v", "3"],
+    ["commit", "-m2", "-v", "C"]
+);
+skip_flags!(
+    skip_flags_checkout,
+    ["checkout", "-b", "2", "3"],
+    ["checkout", "-b", "2", "C"]
+);
+skip_flags!(
+    skip_flags_diff,
+    ["diff", "-U", "2", "3"],
+    ["diff", "-U", "2", "C"]
+);
+skip_flags!(
+    skip_flags_value_is_flag,
+    ["commit", "-m", "-m", "3"],
+    ["commit", "-m", "-m", "C"]
+);
+skip_flags!(
+    skip_flags_after_dashdash,
+    ["commit", "--", "-m", "3"],
+    ["commit", "--", "-m", "C"]
+);
 
 // Running git reset with a number will make git-nu take the one on
 // the right.
@@ -351,6 +569,59 @@ Untracked files:
     );
 });
 
+// Same, but with the `-C` flag given to `git-nu` itself.
+test!(
+    different_workspace_nu_flag,
+    |t| {
+        t.sh("", "mkdir one two");
+        t.sh("one", "git init -b one");
+        t.sh("two", "git init -b two");
+        t.sh("one", "touch gold silver");
+        t.sh("two", "touch bronze");
+        t.sh("two", "git nu -C ../one status");
+        t.sh("two", "git nu -C ../one add 2");
+        let status = t.sh("two", "git nu -C ../one status --short");
+        assert_eq!(status.stdout, "1  A  silver\n2  ?? gold\n");
+    },
+    "two",
+    ["-C", "../one", "add", "2"],
+    ["-C", "../one", "add", "gold"]
+);
+
+// Repositories chosen by `--git-dir` and `--work-tree`, or through the
+// environment, from outside of the workspace.
+test!(explicit_git_dir, |t| {
+    t.sh("", "mkdir -p one/src two");
+    t.sh("one", "git init -b one");
+    t.sh("one", "touch gold src/silver");
+    let flags = "--git-dir=../one/.git --work-tree=../one";
+    t.sh("two", format!("git nu {flags} status"));
+    t.sh("two", format!("git nu {flags} add 2"));
+    let status = t.sh("one", "git status --short");
+    assert_eq!(status.stdout, "A  src/silver\n?? gold\n");
+
+    let env = "GIT_DIR=../one/.git GIT_WORK_TREE=../one";
+    t.sh("two", format!("{env} git nu status"));
+    t.sh("two", format!("{env} git nu add 2"));
+    let status = t.sh("one", "git status --short");
+    assert_eq!(status.stdout, "A  gold\nA  src/silver\n");
+});
+
+// `status.short` set from anywhere makes for a short status.
+test!(
+    status_short_config,
+    |t| {
+        t.sh("", "git init -b main");
+        t.sh("", "touch A B C");
+        t.sh("", "git add A");
+        let status = t.sh("", "git nu -c status.short=true status");
+        assert_eq!(status.stdout, "1  A  A\n2  ?? B\n3  ?? C\n");
+        let _ = t.gitnu("", ["-c", "status.short=true", "status"]);
+    },
+    ["add", "untracked"],
+    ["add", "B", "C"]
+);
+
 // git aliases
 test!(aliases, |t| {
     t.sh("", "git init -b main");
@@ -366,6 +637,595 @@ nothing to commit, working tree clean\n"
     );
 });
 
+// Aliases from any scope are followed to the command they run, along
+// with the flags they imply.
+test!(
+    alias_implied_flags,
+    |t| {
+        t.sh("", "git init -b main");
+        t.sh("", "git config alias.st 'status --short'");
+        t.sh("", "git config alias.s st");
+        t.sh("", "git config alias.l 'log -n'");
+        t.sh("", "touch A B C");
+        let _ = t.gitnu("", ["s"]);
+        let cache = fs::read_to_string(t.dir.join(".git/gitnu.txt")).unwrap();
+        assert!(cache.ends_with("untracked\t??\t\tC\n"));
+    },
+    ["l", "2", "3"],
+    ["l", "2", "C"]
+);
+
+// Shell aliases still get their numbers expanded.
+test!(
+    alias_shell,
+    |t| {
+        t.sh("", "git init -b main");
+        t.sh("", "git config alias.x '!echo'");
+        t.sh("", "touch A B C");
+        let _ = t.gitnu("", ["status"]);
+        assert_eq!(t.sh("", "git nu x 2-3").stdout, "B C\n");
+        assert_eq!(t.sh("", "git nu x all M '?'").stdout, "all M ?\n");
+    },
+    ["x", "-m", "1"],
+    ["x", "-m", "A"]
+);
+
+// Git aliases win over the subcommands that gitnu runs on its own.
+test!(alias_over_native, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "touch A && git add A && git commit -m A");
+    t.sh("", "git config alias.ls 'log --oneline --no-decorate'");
+    let stdout = t.sh("", "git nu ls").stdout;
+    let expected = t.sh("", "git log --oneline --no-decorate").stdout;
+    assert_eq!(stdout, format!("1  {expected}"));
+    let head = t.sh("", "git rev-parse --short HEAD").stdout;
+    let parsed = t.gitnu_parse("", ["show", "1"]).unwrap();
+    assert_eq!(parsed, ["show", head.trim()]);
+});
+
+// `^` only leaves files out where it can't exclude a revision instead.
+test!(
+    exclude_only_paths,
+    |t| {
+        t.sh("", "git init -b main");
+        t.sh("", "touch A B C");
+        let _ = t.gitnu("", ["status"]);
+        let parsed = t.gitnu_parse("", ["add", "1-3", "^2"]).unwrap();
+        assert_eq!(parsed, ["add", "A", "C"]);
+        let parsed = t.gitnu_parse("", ["log", "^2", "HEAD"]).unwrap();
+        assert_eq!(parsed, ["log", "^2", "HEAD"]);
+    },
+    ["log", "main", "^1", "--", "1-3", "!2"],
+    ["log", "main", "^1", "--", "A", "C"]
+);
+
+// `pager.<cmd>` turns paging of numbered output on or off, or picks the
+// pager, just like it does for git.
+test!(pager_config, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "git commit --allow-empty -m x");
+    t.sh("", "git config core.pager 'sed s/^/paged:/'");
+    let tty = |cmd: &str| {
+        let cmd = format!("script -qec '{cmd}' /dev/null | tr -d '\\r'");
+        t.sh("", cmd).stdout.replace("\x1b[32m", "").replace("\x1b[m", "")
+    };
+    assert_eq!(tty("git nu branch"), "paged:1  * main\n");
+    assert_eq!(tty("git nu -P branch"), "1  * main\n");
+    t.sh("", "git config pager.branch false");
+    assert_eq!(tty("git nu branch"), "1  * main\n");
+    t.sh("", "git config pager.branch \"sed s/^/own:/\"");
+    assert_eq!(tty("git nu branch"), "own:1  * main\n");
+    assert_eq!(tty("GIT_PAGER=cat git nu branch"), "1  * main\n");
+    t.sh("", "git config pager.status true");
+    assert!(tty("git nu status").starts_with("paged:On branch main\n"));
+});
+
+// `git nu log` numbers commits, which other commands can then use.
+test!(log_numbered_commits, |t| {
+    t.sh("", "git init -b main");
+    for m in ["A", "B", "C"] {
+        t.sh("", format!("touch {m} && git add {m} && git commit -m {m}"));
+    }
+    let sha = |rev: &str| t.sh("", format!("git rev-parse {rev}")).stdout;
+    let (c, b, a) = (sha("HEAD"), sha("HEAD~1"), sha("HEAD~2"));
+    let (c, b, a) = (c.trim(), b.trim(), a.trim());
+    let short = |v: &str| v[..7].to_string();
+    let stdout = t.sh("", "git nu log --oneline --no-decorate").stdout;
+    let expected = [(c, "C"), (b, "B"), (a, "A")]
+        .iter()
+        .enumerate()
+        .map(|(i, (v, m))| format!("{: <3}{} {m}\n", i + 1, short(v)))
+        .collect::<String>();
+    assert_eq!(stdout, expected);
+    t.gitnu("", ["log", "--oneline"]).unwrap();
+    let parsed = t.gitnu_parse("", ["show", "2"]).unwrap();
+    assert_eq!(parsed, ["show", &short(b)]);
+    let parsed = t.gitnu_parse("", ["cherry-pick", "2-3", "^3"]).unwrap();
+    assert_eq!(parsed, ["cherry-pick", &short(b)]);
+    let parsed = t.gitnu_parse("", ["diff", "3..1", "--", "2"]).unwrap();
+    assert_eq!(
+        parsed,
+        ["diff", &format!("{}..{}", short(a), short(c)), "--", "2"]
+    );
+    let parsed = t.gitnu_parse("", ["log", "2...", "-n", "1"]).unwrap();
+    assert_eq!(parsed, ["log", &format!("{}...", short(b)), "-n", "1"]);
+
+    // normal output numbers the `commit` lines only
+    let stdout = t.sh("", "git nu log -n 1 --no-decorate").stdout;
+    assert!(stdout.starts_with(&format!("1  commit {c}\nAuthor: ")));
+    let parsed = t.gitnu_parse("", ["show", "1"]).unwrap();
+    assert_eq!(parsed, ["show", c]);
+
+    // everything else is printed as it is, so diffs can still be applied
+    let stdout = t.sh("", "git nu log -p -n 1 --no-decorate").stdout;
+    let expected = t.sh("", "git log -p -n 1 --no-decorate").stdout;
+    assert_eq!(stdout, format!("1  {expected}"));
+
+    // formats of the user's own are printed as they are, and leave the
+    // numbers of the last listing alone
+    for format in ["--format=%H", "--pretty=format:%s%n%h", "--format=email"] {
+        let stdout = t.sh("", format!("git nu log {format}")).stdout;
+        assert_eq!(stdout, t.sh("", format!("git log {format}")).stdout);
+    }
+    let parsed = t.gitnu_parse("", ["show", "1"]).unwrap();
+    assert_eq!(parsed, ["show", c]);
+});
+
+// `git nu branch` and `git nu tag` number refs, which are kept apart
+// from files.
+test!(refs_numbered, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "touch A && git add A && git commit -m A");
+    t.sh("", "git branch a && git branch b && git branch c");
+    let stdout = t.sh("", "git nu branch").stdout;
+    assert_eq!(stdout, "1    a\n2    b\n3    c\n4  * main\n");
+    t.gitnu("", ["branch"]).unwrap();
+    t.gitnu("", ["status"]).unwrap();
+    let parsed = t.gitnu_parse("", ["branch", "-D", "2-3"]).unwrap();
+    assert_eq!(parsed, ["branch", "-D", "b", "c"]);
+    let parsed = t.gitnu_parse("", ["switch", "1"]).unwrap();
+    assert_eq!(parsed, ["switch", "a"]);
+
+    // acting on refs keeps the numbers
+    t.gitnu("", ["branch", "-D", "3"]).unwrap();
+    let parsed = t.gitnu_parse("", ["merge", "2", "4"]).unwrap();
+    assert_eq!(parsed, ["merge", "b", "main"]);
+
+    t.sh("", "git tag v1 && git tag v2");
+    assert_eq!(t.sh("", "git nu tag -l").stdout, "1  v1\n2  v2\n");
+    t.gitnu("", ["tag"]).unwrap();
+    let parsed = t.gitnu_parse("", ["tag", "-d", "2"]).unwrap();
+    assert_eq!(parsed, ["tag", "-d", "v2"]);
+});
+
+// `git nu stash list` numbers stashes, which expand to `stash@{N}`.
+test!(stash_numbered, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "touch A && git add A && git commit -m A");
+    for i in 1..=5 {
+        t.sh("", format!("echo {i} > A && git stash push -m s{i}"));
+    }
+    let stdout = t.sh("", "git nu stash list").stdout;
+    let expected = (1..=5)
+        .map(|i| format!("{i}  stash@{{{}}}: On main: s{}\n", i - 1, 6 - i))
+        .collect::<String>();
+    assert_eq!(stdout, expected);
+    t.gitnu("", ["stash", "list"]).unwrap();
+    let parsed = t.gitnu_parse("", ["stash", "show", "-p", "2"]).unwrap();
+    assert_eq!(parsed, ["stash", "show", "-p", "stash@{1}"]);
+    let parsed = t.gitnu_parse("", ["stash", "drop", "3-5"]).unwrap();
+    assert_eq!(
+        parsed,
+        ["stash", "drop", "stash@{2}", "stash@{3}", "stash@{4}"]
+    );
+    let parsed = t.gitnu_parse("", ["stash", "push", "1"]).unwrap();
+    assert_eq!(parsed, ["stash", "push", "1"]);
+
+    // several stashes are dropped one by one, without shifting
+    t.gitnu("", ["stash", "drop", "2", "4"]).unwrap();
+    let stdout = t.sh("", "git stash list --format=%s").stdout;
+    assert_eq!(stdout, "On main: s5\nOn main: s3\nOn main: s1\n");
+
+    // numbers are refused once the stash list has changed
+    assert_eq!(t.gitnu("", ["stash", "drop", "1"]), Err(Error::StaleCache));
+    t.gitnu("", ["stash", "list"]).unwrap();
+    t.gitnu("", ["stash", "drop", "1"]).unwrap();
+    let stdout = t.sh("", "git stash list --format=%s").stdout;
+    assert_eq!(stdout, "On main: s3\nOn main: s1\n");
+});
+
+// Commands that list files number them, like `git nu status` does.
+test!(paths_numbered, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "mkdir src && touch src/a.rs src/b.rs C");
+    t.sh("", "git add . && git commit -m A");
+    t.sh("", "echo x > src/a.rs && echo x > C");
+
+    let stdout = t.sh("", "git nu diff --name-only").stdout;
+    assert_eq!(stdout, "1  C\n2  src/a.rs\n");
+    let stdout = t.sh("", "git nu diff --name-status").stdout;
+    assert_eq!(stdout, "1  M\tC\n2  M\tsrc/a.rs\n");
+    let stdout = t.sh("", "git nu diff --stat").stdout;
+    assert!(stdout.starts_with("1   C        | 1 +\n2   src/a.rs | 1 +\n"));
+
+    // `git diff` lists paths from the root of the workspace
+    t.gitnu("src", ["diff", "--name-only"]).unwrap();
+    let parsed = t.gitnu_parse("src", ["add", "1-2"]).unwrap();
+    assert_eq!(parsed, ["add", "../C", "../src/a.rs"]);
+    t.gitnu("src", ["diff", "--stat"]).unwrap();
+    let parsed = t.gitnu_parse("", ["add", "2"]).unwrap();
+    assert_eq!(parsed, ["add", "src/a.rs"]);
+
+    // while `git ls-files` lists them from the current directory
+    assert_eq!(t.sh("src", "git nu ls-files").stdout, "1  a.rs\n2  b.rs\n");
+    t.gitnu("src", ["ls-files"]).unwrap();
+    let parsed = t.gitnu_parse("", ["add", "2"]).unwrap();
+    assert_eq!(parsed, ["add", "src/b.rs"]);
+
+    t.sh("", "git commit -am B");
+    let stdout = t.sh("", "git nu log --name-only -1").stdout;
+    assert!(stdout.starts_with("commit "));
+    assert!(stdout.ends_with("    B\n\n1  C\n2  src/a.rs\n"));
+    t.gitnu("", ["show", "--name-only", "HEAD~1"]).unwrap();
+    let parsed = t.gitnu_parse("", ["add", "1-3"]).unwrap();
+    assert_eq!(parsed, ["add", "C", "src/a.rs", "src/b.rs"]);
+    t.gitnu("", ["log", "--oneline", "--stat"]).unwrap();
+    let parsed = t.gitnu_parse("", ["add", "1-3"]).unwrap();
+    assert_eq!(parsed, ["add", "C", "src/a.rs", "src/b.rs"]);
+
+    // subjects in a format of the user's own look just like file names
+    let stdout = t.sh("", "git nu log -n1 --format=%s --name-only").stdout;
+    assert_eq!(stdout, "B\n\nC\nsrc/a.rs\n");
+    let parsed = t.gitnu_parse("", ["add", "1-3"]).unwrap();
+    assert_eq!(parsed, ["add", "C", "src/a.rs", "src/b.rs"]);
+    // and a listing of nothing leaves the numbers alone too
+    t.gitnu("", ["diff", "--name-only"]).unwrap();
+    let parsed = t.gitnu_parse("", ["add", "1"]).unwrap();
+    assert_eq!(parsed, ["add", "C"]);
+});
+
+// Output that isn't UTF-8, such as a diff of a Latin-1 file, is printed
+// in full, as git prints it.
+test!(non_utf8_output, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "printf 'caf\\351 x\\nx\\n' > A && printf 'x\\n' > B");
+    t.sh("", "git add A B && git commit -m A");
+    let stdout = t.sh("", "git nu log -p -n 1 --no-decorate").stdout;
+    let expected = t.sh("", "git log -p -n 1 --no-decorate").stdout;
+    assert_eq!(stdout, format!("1  {expected}"));
+    let stdout = t.sh("", "git nu grep -n x").stdout;
+    assert_eq!(stdout, "1  A:1:caf\u{FFFD} x\n2  A:2:x\n3  B:1:x\n");
+});
+
+// `git nu grep` numbers each match, and caches its line number.
+test!(grep_numbered, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "mkdir src && printf 'x\\nfoo\\ny\\nfoo\\n' > src/a.rs");
+    t.sh("", "echo foo > b-1:c && git add .");
+
+    let stdout = t.sh("", "git nu grep -n foo").stdout;
+    assert_eq!(
+        stdout,
+        "1  b-1:c:1:foo\n2  src/a.rs:2:foo\n3  src/a.rs:4:foo\n"
+    );
+    t.gitnu("", ["grep", "-n", "foo"]).unwrap();
+    let cache = fs::read_to_string(t.dir.join(".git/gitnu.txt")).unwrap();
+    assert!(cache.ends_with("\t\t1\tb-1:c\n\t\t2\tsrc/a.rs\n\t\t4\tsrc/a.rs\n"));
+    let parsed = t.gitnu_parse("", ["add", "1", "3"]).unwrap();
+    assert_eq!(parsed, ["add", "b-1:c", "src/a.rs"]);
+
+    let stdout = t.sh("", "git nu grep -n -C1 foo -- src").stdout;
+    assert_eq!(
+        stdout,
+        "   src/a.rs-1-x\n1  src/a.rs:2:foo\n   src/a.rs-3-y\n2  src/a.rs:4:foo\n"
+    );
+    let stdout = t.sh("", "git nu grep --heading -n foo").stdout;
+    assert_eq!(stdout, "   b-1:c\n1  1:foo\n   src/a.rs\n2  2:foo\n3  4:foo\n");
+    let stdout = t.sh("src", "git nu grep -l foo").stdout;
+    assert_eq!(stdout, "1  a.rs\n");
+    let stdout = t.sh("src", "git nu grep -c foo").stdout;
+    assert_eq!(stdout, "1  a.rs:2\n");
+    t.gitnu("src", ["grep", "foo"]).unwrap();
+    let parsed = t.gitnu_parse("", ["add", "2"]).unwrap();
+    assert_eq!(parsed, ["add", "src/a.rs"]);
+});
+
+// Matches come with their line when git shows it, and their file name
+// is found without the file having to exist.
+test!(grep_exact_names, |t| {
+    t.sh("", "git init -b main");
+    t.sh(
+        "",
+        "echo foo > a-1-b && echo foo > c && git add . && git commit -m x",
+    );
+    assert_eq!(t.sh("", "git nu grep foo").stdout, "1  a-1-b:foo\n2  c:foo\n");
+    t.gitnu("", ["grep", "foo"]).unwrap();
+    assert_eq!(t.sh("", "git nu ls").stdout, "1  a-1-b\n2  c\n");
+    assert_eq!(
+        t.sh("", "git nu grep -n foo").stdout,
+        "1  a-1-b:1:foo\n2  c:1:foo\n"
+    );
+    t.gitnu("", ["grep", "-n", "foo"]).unwrap();
+    assert_eq!(t.sh("", "git nu ls").stdout, "1  a-1-b:1\n2  c:1\n");
+
+    t.sh("", "echo x > c && git rm -q a-1-b");
+    assert_eq!(
+        t.sh("", "git nu grep foo HEAD").stdout,
+        "1  HEAD:a-1-b:foo\n2  HEAD:c:foo\n"
+    );
+    t.gitnu("", ["grep", "foo", "HEAD"]).unwrap();
+    assert_eq!(t.sh("", "git nu ls").stdout, "1  a-1-b\n2  c\n");
+    assert_eq!(
+        t.sh("", "git nu grep --heading foo HEAD").stdout,
+        "   HEAD:a-1-b\n1  foo\n   HEAD:c\n2  foo\n"
+    );
+    t.sh("", "git config grep.lineNumber true");
+    assert_eq!(
+        t.sh("", "git nu grep foo HEAD").stdout,
+        "1  HEAD:a-1-b:1:foo\n2  HEAD:c:1:foo\n"
+    );
+    t.gitnu("", ["grep", "foo", "HEAD"]).unwrap();
+    assert_eq!(t.sh("", "git nu ls").stdout, "1  a-1-b:1\n2  c:1\n");
+    t.gitnu("", ["grep", "--no-line-number", "foo", "HEAD"]).unwrap();
+    assert_eq!(t.sh("", "git nu ls").stdout, "1  a-1-b\n2  c\n");
+
+    t.sh("", "echo foo > c && git add c && rm c");
+    t.gitnu("", ["grep", "--cached", "foo"]).unwrap();
+    assert_eq!(t.sh("", "git nu ls").stdout, "1  c:1\n");
+});
+
+// `git nu edit` opens cached files in git's editor, at the line of a
+// `git nu grep` match.
+test!(edit_cached_files, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "git config core.editor 'echo editing'");
+    t.sh("", "printf 'x\\nfoo\\n' > A && touch B && git add A");
+    let _ = t.gitnu("", ["status"]);
+    let stdout = t.sh("", "env -u GIT_EDITOR git nu edit 1-2 C").stdout;
+    assert_eq!(stdout, "editing A B C\n");
+    let _ = t.gitnu("", ["grep", "-n", "foo"]);
+    let stdout = t.sh("", "GIT_EDITOR='echo open' git nu edit 1").stdout;
+    assert_eq!(stdout, "open +2 A\n");
+    t.sh("", "mkdir src");
+    let stdout = t.sh("src", "GIT_EDITOR='echo open' git nu edit 1").stdout;
+    assert_eq!(stdout, "open +2 ../A\n");
+});
+
+// `git nu ls` prints the cached files again, without running status.
+test!(ls_cached_files, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "mkdir src && touch A B && git add A");
+    let _ = t.gitnu("", ["status"]);
+    t.sh("", "touch C");
+    assert_eq!(t.sh("", "git nu ls").stdout, "1  A  A\n2  ?? B\n");
+    assert_eq!(t.sh("src", "git nu ls untracked").stdout, "2  ?? ../B\n");
+    t.sh("", "echo x > A");
+    let _ = t.gitnu("", ["grep", "-n", "x"]);
+    assert_eq!(t.sh("", "git nu ls").stdout, "1  A:1\n");
+});
+
+// `git nu which` resolves numbers to files, and files to numbers.
+test!(which_cached_files, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "mkdir src && touch A 'B C' src/D");
+    let _ = t.gitnu("", ["status"]);
+    assert_eq!(t.sh("", "git nu which 2").stdout, "B C\n");
+    assert_eq!(t.sh("", "git nu which -z 1-2").stdout, "A\0B C\0");
+    assert_eq!(t.sh("src", "git nu which 1 ^2-3").stdout, "../A\n");
+    assert_eq!(t.sh("", "git nu which --index src/ 'B C'").stdout, "3\n2\n");
+    assert_eq!(t.sh("src", "git nu which --index ../A").stdout, "1\n");
+    let output = t.sh("", "git nu which --index 1 E");
+    assert_eq!((output.stdout.as_str(), output.exit_code), ("1\n", Some(1)));
+    let output = t.sh("", "git nu which 4");
+    assert_eq!((output.stdout.as_str(), output.exit_code), ("", Some(1)));
+});
+
+// Paths are cached exactly as they are, even when `git status` quotes
+// them or they look like its own notation.
+test!(status_exact_paths, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "touch M 'a -> b' 'c:d' 'q\"uote' && git add M");
+    t.sh("", "git commit -m x && git mv M N");
+    let which = "git nu which 1-4";
+    let _ = t.gitnu("", ["status"]);
+    assert_eq!(t.sh("", which).stdout, "N\na -> b\nc:d\nq\"uote\n");
+    let _ = t.gitnu("", ["status", "--short"]);
+    assert_eq!(t.sh("", which).stdout, "N\na -> b\nc:d\nq\"uote\n");
+});
+
+// Paths that git quotes for their unusual characters are cached as
+// they are on disk.
+test!(quoted_paths, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "touch 'café menu.txt' 'q\"uote' && git add . && git commit -m x");
+    t.sh("", "echo match | tee 'café menu.txt' 'q\"uote'");
+    let expected = "café menu.txt\nq\"uote\n";
+    for cmd in ["diff --stat", "diff --name-only", "grep -n match"] {
+        t.sh("", format!("git nu {cmd}"));
+        assert_eq!(t.sh("", "git nu which 1 2").stdout, expected);
+    }
+    t.sh("", "git nu add 1 2");
+    let status = t.sh("", "git status --porcelain -z").stdout;
+    assert_eq!(status, "M  café menu.txt\0M  q\"uote\0");
+});
+
+// Renamed files keep both paths: commands that unstage take both, and
+// `:old` or `:new` picks one of them.
+test!(renames_both_paths, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "touch A B && git add . && git commit -m x");
+    t.sh("", "git mv A C && git mv B D");
+    let _ = t.gitnu("", ["status"]);
+    assert_eq!(t.sh("", "git nu which 1:old 1:new 2").stdout, "A\nC\nD\n");
+    assert_eq!(t.sh("", "git nu ls").stdout, "1  R  A -> C\n2  R  B -> D\n");
+    let parse = |args: &[&str]| t.gitnu_parse("", args).unwrap();
+    assert_eq!(parse(&["reset", "1"]), ["reset", "C", "A"]);
+    assert_eq!(parse(&["reset", "1:new"]), ["reset", "C"]);
+    assert_eq!(parse(&["restore", "2", "-S"]), ["restore", "D", "B", "-S"]);
+    assert_eq!(parse(&["restore", "2"]), ["restore", "D"]);
+    assert_eq!(parse(&["add", "2:old"]), ["add", "B"]);
+    t.sh("", "git nu restore --staged 1-2");
+    let status = t.sh("", "git status --short").stdout;
+    assert_eq!(status, " D A\n D B\n?? C\n?? D\n");
+});
+
+// Ignored files are numbered in their own section, and the diffs of
+// `--verbose` are never numbered.
+test!(status_ignored_verbose, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "touch A && git add A && git commit -m x");
+    t.sh("", "printf 'a\\n' > A && git add A && printf '\\tb\\n' >> A");
+    t.sh("", "echo C > .gitignore && touch B C");
+    let status = t.sh("", "git nu status --ignored -vv").stdout;
+    let numbered = status.lines().filter(|v| v.starts_with(char::is_numeric));
+    assert_eq!(numbered.count(), 5);
+    let which = t.sh("", "git nu which 1-6");
+    assert_eq!(which.stdout, "A\nA\n.gitignore\nB\nC\n");
+    assert!(t.sh("", "git nu ls ignored").stdout.ends_with("!! C\n"));
+});
+
+// Every file is cached with its own section, whichever sections are
+// left out, and whatever else is indented among them.
+test!(status_sections, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "echo b > f && touch s && git add . && git commit -m b");
+    t.sh("", "git checkout -b other && echo o > f && git commit -am o");
+    t.sh("", "git checkout main && echo m > f && git commit -am m");
+    t.sh("", "git merge other; printf '\\tx\\n' > n && git add n");
+    t.sh("", "echo s > s && echo i > .gitignore && touch i");
+    t.sh("", "git -c advice.statusHints=true nu status --ignored -vv");
+    let ls = "1  A  n\n2  UU f\n3   M s\n4  ?? .gitignore\n5  !! i\n";
+    assert_eq!(t.sh("", "git nu ls").stdout, ls);
+    t.sh("", "git nu status -uno");
+    assert_eq!(t.sh("", "git nu ls").stdout, "1  A  n\n2  UU f\n3   M s\n");
+    assert_eq!(t.sh("", "git nu ls unstaged").stdout, "3   M s\n");
+});
+
+// Numbers are the same under any of git's translations, which are used
+// for what is printed.
+test!(status_translated, |t| {
+    let de = "LC_ALL=C.UTF-8 LANGUAGE=de";
+    t.sh("", "mkdir repo outside");
+    t.sh("repo", "git init -b main");
+    t.sh("repo", "touch A B 'C D' && git add A && git commit -m x");
+    t.sh("repo", "git mv A E && git add B");
+    t.sh("repo", format!("{de} git nu status"));
+    assert_eq!(t.sh("repo", "git nu which 1-3").stdout, "B\nE\nC D\n");
+    t.sh("repo", format!("{de} git nu status --short"));
+    assert_eq!(t.sh("repo", "git nu which 1-3").stdout, "B\nE\nC D\n");
+    // git picks the language, and leaves out locales that aren't there
+    for env in [de, "LANG=de_DE.UTF-8 LANGUAGE=de", "LANG=xx_XX.UTF-8"] {
+        let status = t.sh("repo", format!("{env} git nu status")).stdout;
+        let status: String = status
+            .lines()
+            .map(|v| v.trim_start_matches(char::is_numeric).to_string() + "\n")
+            .collect();
+        assert_eq!(status, t.sh("repo", format!("{env} git status")).stdout);
+    }
+    // outside of a repository, git is left to report it
+    let output = t.sh("outside", format!("{de} git nu status"));
+    assert_eq!(output.exit_code, Some(128));
+    assert!(!t.dir.join("outside/gitnu.txt").exists());
+});
+
+// The branch header of a short status is printed, but not numbered.
+test!(short_status_branch, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "touch A B && git add A && git commit -m x");
+    t.sh("", "touch C");
+    let status = t.sh("", "git nu status -sb");
+    assert_eq!(status.stdout, "## main\n1  ?? B\n2  ?? C\n");
+    let status = t.sh("", "git nu status --short --branch --show-stash");
+    assert_eq!(status.stdout, "## main\n1  ?? B\n2  ?? C\n");
+    assert_eq!(t.sh("", "git nu which 1 2").stdout, "B\nC\n");
+});
+
+// Conflicts are numbered where the short format lists them, which is
+// in order of their paths.
+test!(short_status_conflicts, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "echo b > f && touch s && git add . && git commit -m b");
+    t.sh("", "git checkout -b other && echo o > f && git commit -am o");
+    t.sh("", "git checkout main && echo m > f && git commit -am m");
+    t.sh("", "git merge other; touch n && git add n && echo s > s");
+    let status = t.sh("", "git nu status -s");
+    assert_eq!(status.stdout, "1  UU f\n2  A  n\n3   M s\n");
+    assert_eq!(t.sh("", "git nu which 1-3").stdout, "f\nn\ns\n");
+});
+
+// Formats that are meant for scripts, or that list several files on a
+// line, are printed as git prints them, and leave the cache as it is.
+test!(status_unnumbered_formats, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "touch A B && git add A");
+    t.sh("", "git nu status");
+    let cache = fs::read(t.dir.join(".git/gitnu.txt")).unwrap();
+    for args in ["-s -z", "--porcelain", "--porcelain=v2", "--column"] {
+        let status = t.sh("", format!("git nu status {args}"));
+        assert_eq!(
+            status.stdout,
+            t.sh("", format!("git status {args}")).stdout
+        );
+    }
+    let status = t.sh("", "git -c column.status=always nu status");
+    assert!(!status.stdout.contains("1\t"));
+    assert_eq!(fs::read(t.dir.join(".git/gitnu.txt")).unwrap(), cache);
+    assert_eq!(t.sh("", "git nu which 1 2").stdout, "A\nB\n");
+});
+
+// Git's own lines are printed as they are, such as the one about a
+// sparse checkout.
+test!(status_sparse_checkout, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "mkdir a b && touch a/x b/y && git add . && git commit -m x");
+    t.sh("", "git sparse-checkout set a && touch a/z");
+    let status = t.sh("", "git nu status").stdout;
+    assert!(status.contains("You are in a sparse checkout with 50%"));
+    assert!(status.contains("1\ta/z\n"));
+    assert_eq!(t.sh("", "git nu which 1").stdout, "a/z\n");
+});
+
+// `git nu exec` runs other programs with numbers expanded.
+test!(exec_other_programs, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "mkdir src && touch A 'B C' src/D");
+    let _ = t.gitnu("", ["status"]);
+    assert_eq!(t.sh("", "git nu exec echo 1-2").stdout, "A B C\n");
+    assert_eq!(t.sh("src", "git nu -x echo 1 \\\\3").stdout, "../A 3\n");
+    assert_eq!(t.sh("", "git nu -C src -x echo 1").stdout, "../A\n");
+    // words that select files elsewhere are left to the program
+    let stdout = t.sh("", "git nu exec echo all done, grep M ^1 @*").stdout;
+    assert_eq!(stdout, "all done, grep M ^1 @*\n");
+    assert_eq!(t.sh("", "git nu ls untracked").stdout.lines().count(), 3);
+    assert_eq!(t.sh("", "git nu exec").exit_code, Some(1));
+    assert_eq!(t.sh("", "git nu exec not-a-program").exit_code, Some(1));
+});
+
+// Completion scripts offer commands, and numbers with their files.
+test!(completions, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "touch A B");
+    let _ = t.gitnu("", ["status"]);
+    t.sh("", "git nu completions bash > .git/c.bash");
+    let complete = |words: &str| {
+        let cmd = format!(
+            "bash -c 'source .git/c.bash; COMP_WORDS=({words}); \
+            COMP_CWORD=$((${{#COMP_WORDS[@]}} - 1)); _git_nu; \
+            printf \"%s\\n\" \"${{COMPREPLY[@]}}\"'"
+        );
+        t.sh("", cmd).stdout
+    };
+    assert_eq!(complete("git nu add \"\""), "1  ?? A\n2  ?? B\n");
+    assert_eq!(complete("git nu add 2"), "2\n");
+    assert_eq!(complete("git nu whi"), "which\n");
+    assert_eq!(complete("git-nu sta"), "status\nstash\n");
+    for shell in ["zsh", "fish"] {
+        let script = t.sh("", format!("git nu completions {shell}")).stdout;
+        assert!(script.contains("edit ls which completions exec status add "));
+    }
+    assert_eq!(t.sh("", "git nu completions tcsh").exit_code, Some(1));
+});
+
 macro_rules! status_test {
     ($name:ident, $setup:expr, $inout:expr, $stdout:expr) => {
         test!($name, |t| {
@@ -654,3 +1514,21 @@ test!(
     ["add", "17-20"],
     ["add", "B6", "B7", "B8", "B9"]
 );
+
+// Files past the ones that are numbered are still cached, so selections
+// that aren't numbers cover all of them.
+test!(max_cache_select_all, |t| {
+    t.sh("", "git init -b main");
+    t.sh("", "touch f && git add f && git commit -m x");
+    t.sh("", "for i in $(seq 10 34); do echo $i > f$i; done");
+    t.sh("", "git add . && git commit -m y");
+    t.sh("", "for i in $(seq 10 34); do echo x >> f$i; done");
+    t.sh("", "git nu status");
+    let count = |cmd: &str| t.sh("", cmd).stdout.lines().count();
+    assert_eq!(count("git nu which all"), 25);
+    assert_eq!(count("git nu which M"), 25);
+    assert_eq!(count("git nu which '@f3*'"), 5);
+    assert_eq!(t.sh("", "git nu which 23-").stdout, "f32\nf33\nf34\n");
+    let parse = t.gitnu_parse("", ["add", "2020-2024"]).unwrap();
+    assert_eq!(parse, ["add", "2020-2024"]);
+});